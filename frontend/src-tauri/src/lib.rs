@@ -40,6 +40,9 @@ pub fn run() {
             let handle = app.handle();
 
             panic_hook::setup(handle.clone());
+            share_screen::events::init(handle.clone());
+            share_screen::settings::setup(handle);
+            share_screen::remote::setup(handle);
             app_window::setup_window::setup(&app);
             autostart::setup(&app);
             let _ = tray::setup_tray(&app);
@@ -57,11 +60,26 @@ pub fn run() {
             share_screen::command::get_windows,
             share_screen::command::get_monitor_by_id,
             share_screen::command::get_window_by_id,
+            share_screen::command::get_cameras,
             share_screen::command::start_monitor_preview,
             share_screen::command::start_window_preview,
+            share_screen::command::switch_preview_source,
             share_screen::command::stop_preview,
+            share_screen::command::add_annotation,
+            share_screen::command::undo_annotation,
+            share_screen::command::clear_annotations,
+            share_screen::command::allow_viewer_annotations,
+            share_screen::command::get_stream_stats,
+            share_screen::command::get_webrtc_settings,
+            share_screen::command::set_webrtc_settings,
             share_screen::command::get_preview_offer,
             share_screen::command::accept_preview_answer,
+            share_screen::command::answer_preview_offer,
+            share_screen::command::send_preview_control,
+            share_screen::command::grant_remote_control,
+            share_screen::command::deny_remote_control,
+            share_screen::command::revoke_remote_control,
+            share_screen::command::close_preview_viewer,
             share_screen::command::add_preview_ice_candidate
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,319 @@
+// src/share_screen/annotation.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::filter::FrameFilter;
+use crate::share_screen::frame::{PixelFormat, VideoFrame};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Position in source pixels, the size of the captured window or monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Presenter drawing. Colours are RGBA, widths are in source pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    Stroke {
+        points: Vec<Point>,
+        color: [u8; 4],
+        width: f32,
+    },
+    Arrow {
+        from: Point,
+        to: Point,
+        color: [u8; 4],
+        width: f32,
+    },
+    Rectangle {
+        from: Point,
+        to: Point,
+        color: [u8; 4],
+        width: f32,
+    },
+    /// Filled, usually with a translucent colour.
+    Highlight {
+        from: Point,
+        to: Point,
+        color: [u8; 4],
+    },
+}
+
+/// Edit of an annotation layer, as exchanged with viewers over the control
+/// channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AnnotationEvent {
+    Add { shape: Shape },
+    Undo,
+    Clear,
+}
+
+impl AnnotationEvent {
+    /// Returns false when it changed nothing, an undo with no shapes.
+    pub fn apply(&self, layer: &AnnotationLayer) -> bool {
+        match self {
+            AnnotationEvent::Add { shape } => {
+                layer.add(shape.clone());
+                true
+            }
+            AnnotationEvent::Undo => layer.undo(),
+            AnnotationEvent::Clear => {
+                layer.clear();
+                true
+            }
+        }
+    }
+}
+
+/// Shapes drawn over one stream. Shared between the commands that edit it,
+/// the encoder thread that composites it and the local overlay.
+#[derive(Debug, Default)]
+pub struct AnnotationLayer {
+    shapes: Mutex<Vec<Shape>>,
+    revision: AtomicU64,
+    // Viewers draw only once the sharer allows it, like remote control
+    viewers_allowed: AtomicBool,
+}
+
+impl AnnotationLayer {
+    pub fn set_viewers_allowed(&self, allowed: bool) {
+        self.viewers_allowed.store(allowed, Ordering::Relaxed);
+    }
+
+    pub fn viewers_allowed(&self) -> bool {
+        self.viewers_allowed.load(Ordering::Relaxed)
+    }
+
+    pub fn add(&self, shape: Shape) {
+        self.shapes.lock().unwrap().push(shape);
+        self.revision.fetch_add(1, Ordering::Release);
+    }
+
+    /// Removes the most recent shape. Returns false when there was none.
+    pub fn undo(&self) -> bool {
+        let removed = self.shapes.lock().unwrap().pop().is_some();
+        if removed {
+            self.revision.fetch_add(1, Ordering::Release);
+        }
+        removed
+    }
+
+    pub fn clear(&self) {
+        self.shapes.lock().unwrap().clear();
+        self.revision.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn shapes(&self) -> Vec<Shape> {
+        self.shapes.lock().unwrap().clone()
+    }
+
+    /// Changes on every edit, so consumers can tell when to redraw.
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::Acquire)
+    }
+}
+
+/// Composites the annotation layer into BGRA frames. Runs first in the
+/// chain, while frames still cover the whole source.
+pub struct AnnotationFilter {
+    layer: Arc<AnnotationLayer>,
+    source_width: u32,
+    source_height: u32,
+}
+
+impl AnnotationFilter {
+    pub fn new(layer: Arc<AnnotationLayer>, source_width: u32, source_height: u32) -> Self {
+        Self {
+            layer,
+            source_width: source_width.max(1),
+            source_height: source_height.max(1),
+        }
+    }
+}
+
+impl FrameFilter for AnnotationFilter {
+    fn name(&self) -> &'static str {
+        "annotation"
+    }
+
+    fn revision(&self) -> u64 {
+        self.layer.revision()
+    }
+
+    fn apply(&mut self, mut frame: VideoFrame) -> Result<VideoFrame> {
+        let shapes = self.layer.shapes();
+        if shapes.is_empty() {
+            return Ok(frame);
+        }
+
+        if frame.format != PixelFormat::Bgra {
+            return Err(CaptureError::ProcessingError(format!(
+                "Expected a BGRA frame, got {:?}",
+                frame.format
+            )));
+        }
+
+        let mut canvas = Canvas {
+            scale_x: frame.width as f32 / self.source_width as f32,
+            scale_y: frame.height as f32 / self.source_height as f32,
+            frame: &mut frame,
+        };
+        for shape in &shapes {
+            canvas.draw(shape);
+        }
+
+        Ok(frame)
+    }
+}
+
+/// Two short segments forming the head of an arrow pointing at `to`.
+pub fn arrow_head(from: Point, to: Point, width: f32) -> [(Point, Point); 2] {
+    let length = (width * 4.0).max(12.0);
+    let angle = (from.y - to.y).atan2(from.x - to.x);
+    let spread = 25f32.to_radians();
+
+    [angle - spread, angle + spread].map(|a| {
+        (
+            to,
+            Point {
+                x: to.x + length * a.cos(),
+                y: to.y + length * a.sin(),
+            },
+        )
+    })
+}
+
+struct Canvas<'a> {
+    frame: &'a mut VideoFrame,
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl Canvas<'_> {
+    fn draw(&mut self, shape: &Shape) {
+        match shape {
+            Shape::Stroke {
+                points,
+                color,
+                width,
+            } => {
+                if let [point] = points.as_slice() {
+                    self.line(*point, *point, *width, *color);
+                }
+                for pair in points.windows(2) {
+                    self.line(pair[0], pair[1], *width, *color);
+                }
+            }
+            Shape::Arrow {
+                from,
+                to,
+                color,
+                width,
+            } => {
+                self.line(*from, *to, *width, *color);
+                for (a, b) in arrow_head(*from, *to, *width) {
+                    self.line(a, b, *width, *color);
+                }
+            }
+            Shape::Rectangle {
+                from,
+                to,
+                color,
+                width,
+            } => {
+                let corners = [
+                    *from,
+                    Point { x: to.x, y: from.y },
+                    *to,
+                    Point { x: from.x, y: to.y },
+                ];
+                for i in 0..4 {
+                    self.line(corners[i], corners[(i + 1) % 4], *width, *color);
+                }
+            }
+            Shape::Highlight { from, to, color } => {
+                let (x0, y0) = self.to_frame(*from);
+                let (x1, y1) = self.to_frame(*to);
+                let inside = |_, _| true;
+                self.fill(
+                    x0.min(x1),
+                    y0.min(y1),
+                    x0.max(x1),
+                    y0.max(y1),
+                    inside,
+                    *color,
+                );
+            }
+        }
+    }
+
+    fn to_frame(&self, point: Point) -> (f32, f32) {
+        (point.x * self.scale_x, point.y * self.scale_y)
+    }
+
+    // Round capped segment, every pixel within half the width of it is set
+    fn line(&mut self, from: Point, to: Point, width: f32, color: [u8; 4]) {
+        let (ax, ay) = self.to_frame(from);
+        let (bx, by) = self.to_frame(to);
+        let radius = (width * self.scale_x.min(self.scale_y) / 2.0).max(0.5);
+
+        let (dx, dy) = (bx - ax, by - ay);
+        let length_sq = dx * dx + dy * dy;
+
+        self.fill(
+            ax.min(bx) - radius,
+            ay.min(by) - radius,
+            ax.max(bx) + radius,
+            ay.max(by) + radius,
+            |px, py| {
+                let t = if length_sq > 0.0 {
+                    (((px - ax) * dx + (py - ay) * dy) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let (cx, cy) = (ax + t * dx - px, ay + t * dy - py);
+                cx * cx + cy * cy <= radius * radius
+            },
+            color,
+        );
+    }
+
+    /// Blends `color` into every pixel of the box whose centre passes `inside`.
+    fn fill(
+        &mut self,
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+        inside: impl Fn(f32, f32) -> bool,
+        [r, g, b, a]: [u8; 4],
+    ) {
+        let x0 = left.floor().max(0.0) as usize;
+        let y0 = top.floor().max(0.0) as usize;
+        let x1 = (right.ceil().max(0.0) as usize).min(self.frame.width as usize);
+        let y1 = (bottom.ceil().max(0.0) as usize).min(self.frame.height as usize);
+
+        let stride = self.frame.strides[0];
+        let alpha = a as u32;
+        let blend = |dst: &mut u8, src: u8| {
+            *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+        };
+
+        for y in y0..y1 {
+            let row = &mut self.frame.data[y * stride..];
+            for x in x0..x1 {
+                if !inside(x as f32 + 0.5, y as f32 + 0.5) {
+                    continue;
+                }
+                let px = &mut row[x * 4..x * 4 + 4];
+                blend(&mut px[0], b);
+                blend(&mut px[1], g);
+                blend(&mut px[2], r);
+            }
+        }
+    }
+}
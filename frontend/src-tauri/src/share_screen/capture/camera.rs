@@ -0,0 +1,219 @@
+// src/share_screen/capture/camera.rs
+use crate::share_screen::dto::{CameraInfo, CaptureError, Result};
+use crate::share_screen::frame::VideoFrame;
+use crate::share_screen::pip::CameraFeed;
+use std::sync::Arc;
+use windows::Win32::Media::MediaFoundation::*;
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx, CoTaskMemFree};
+use windows::core::{GUID, PWSTR};
+
+// Video capture devices, in the order Media Foundation lists them
+unsafe fn video_devices() -> Result<Vec<IMFActivate>> {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        MFStartup(MF_VERSION, MFSTARTUP_FULL)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let mut attributes: Option<IMFAttributes> = None;
+        MFCreateAttributes(&mut attributes, 1)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        let attributes = attributes.unwrap();
+        attributes
+            .SetGUID(
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE,
+                &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_GUID,
+            )
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let mut devices: *mut Option<IMFActivate> = std::ptr::null_mut();
+        let mut count = 0u32;
+        MFEnumDeviceSources(&attributes, &mut devices, &mut count)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        if devices.is_null() {
+            return Ok(vec![]);
+        }
+
+        let list = (0..count as usize)
+            .filter_map(|i| (*devices.add(i)).take())
+            .collect();
+        CoTaskMemFree(Some(devices as *const _));
+
+        Ok(list)
+    }
+}
+
+unsafe fn device_string(device: &IMFActivate, key: &GUID) -> Result<String> {
+    unsafe {
+        let mut value = PWSTR::null();
+        let mut length = 0u32;
+        device
+            .GetAllocatedString(key, &mut value, &mut length)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let text = value.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(value.0 as *const _));
+        Ok(text)
+    }
+}
+
+pub fn enumerate_cameras() -> Result<Vec<CameraInfo>> {
+    unsafe {
+        video_devices()?
+            .iter()
+            .map(|device| {
+                Ok(CameraInfo {
+                    id: device_string(
+                        device,
+                        &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+                    )?,
+                    name: device_string(device, &MF_DEVSOURCE_ATTRIBUTE_FRIENDLY_NAME)?,
+                })
+            })
+            .collect()
+    }
+}
+
+// Opens the camera with the video processor on, so any native format comes
+// out as RGB32
+unsafe fn open_reader(device_id: Option<&str>) -> Result<(IMFSourceReader, u32, u32, i32)> {
+    unsafe {
+        let devices = video_devices()?;
+        let device = match device_id {
+            Some(id) => devices.iter().find(|device| {
+                device_string(
+                    device,
+                    &MF_DEVSOURCE_ATTRIBUTE_SOURCE_TYPE_VIDCAP_SYMBOLIC_LINK,
+                )
+                .is_ok_and(|link| link == id)
+            }),
+            None => devices.first(),
+        }
+        .ok_or_else(|| CaptureError::SourceNotFound(device_id.unwrap_or("camera").to_string()))?;
+
+        let source: IMFMediaSource = device
+            .ActivateObject()
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let mut attributes: Option<IMFAttributes> = None;
+        MFCreateAttributes(&mut attributes, 1)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        let attributes = attributes.unwrap();
+        attributes
+            .SetUINT32(&MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, 1)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let reader = MFCreateSourceReaderFromMediaSource(&source, &attributes)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        let media_type =
+            MFCreateMediaType().map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        media_type
+            .SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        media_type
+            .SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        reader
+            .SetCurrentMediaType(stream, None, &media_type)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+
+        let current = reader
+            .GetCurrentMediaType(stream)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        let size = current
+            .GetUINT64(&MF_MT_FRAME_SIZE)
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        let (width, height) = ((size >> 32) as u32, size as u32);
+
+        // RGB is bottom-up unless the type says otherwise
+        let stride = current
+            .GetUINT32(&MF_MT_DEFAULT_STRIDE)
+            .map(|stride| stride as i32)
+            .unwrap_or(-(width as i32 * 4));
+
+        Ok((reader, width, height, stride))
+    }
+}
+
+/// Reads the camera on its own thread into `feed` until it is stopped.
+/// Returns once the camera is open, with the error if it could not be.
+pub fn start_camera(device_id: Option<String>, feed: Arc<CameraFeed>) -> Result<()> {
+    let (opened_tx, opened_rx) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || unsafe {
+        let (reader, width, height, stride) = match open_reader(device_id.as_deref()) {
+            Ok(opened) => {
+                let _ = opened_tx.send(Ok(()));
+                opened
+            }
+            Err(e) => {
+                let _ = opened_tx.send(Err(e));
+                return;
+            }
+        };
+        let row_bytes = width as usize * 4;
+        let stream = MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32;
+        let mut frame_number = 0u64;
+
+        while !feed.is_stopped() {
+            let mut flags = 0u32;
+            let mut sample: Option<IMFSample> = None;
+            if let Err(e) =
+                reader.ReadSample(stream, 0, None, Some(&mut flags), None, Some(&mut sample))
+            {
+                eprintln!("[Camera] Read error: {:?}", e);
+                break;
+            }
+
+            if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+                break;
+            }
+            let Some(buffer) = sample.and_then(|s| s.ConvertToContiguousBuffer().ok()) else {
+                continue;
+            };
+
+            let mut data: *mut u8 = std::ptr::null_mut();
+            let mut length = 0u32;
+            if buffer.Lock(&mut data, None, Some(&mut length)).is_err() {
+                continue;
+            }
+            let bytes = std::slice::from_raw_parts(data, length as usize);
+
+            let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+            let pitch = stride.unsigned_abs() as usize;
+            if bytes.len() >= pitch * height as usize {
+                for y in 0..height as usize {
+                    let row = if stride < 0 {
+                        height as usize - 1 - y
+                    } else {
+                        y
+                    };
+                    pixels.extend_from_slice(&bytes[row * pitch..row * pitch + row_bytes]);
+                }
+            }
+            let _ = buffer.Unlock();
+
+            if pixels.len() != row_bytes * height as usize {
+                continue;
+            }
+
+            match VideoFrame::bgra(width, height, row_bytes, pixels) {
+                Ok(frame) => {
+                    feed.publish(frame.with_timing(std::time::Duration::ZERO, frame_number));
+                    frame_number += 1;
+                }
+                Err(e) => eprintln!("[Camera] Bad frame: {:?}", e),
+            }
+        }
+
+        println!("[Camera] Camera released");
+    });
+
+    opened_rx.recv().unwrap_or_else(|_| {
+        Err(CaptureError::PlatformError(
+            "Camera thread ended before opening".to_string(),
+        ))
+    })
+}
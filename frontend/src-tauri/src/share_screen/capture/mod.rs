@@ -2,10 +2,15 @@
 #[cfg(windows)]
 pub mod windows;
 
+#[cfg(windows)]
+pub mod camera;
+
 #[cfg(windows)]
 pub mod monitor;
 
 use crate::share_screen::dto::*;
+use crate::share_screen::pip::CameraFeed;
+use std::sync::Arc;
 
 #[cfg(windows)]
 pub use self::windows::WindowCapture;
@@ -120,10 +125,10 @@ impl CaptureDevice {
         &self,
         hmonitor: isize,
         config: CaptureConfig,
-        video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        outputs: CaptureOutputs,
     ) -> Result<()> {
         self.monitor_capture
-            .start_capture(hmonitor, config, video_tx)
+            .start_capture(hmonitor, config, outputs)
             .await
     }
 
@@ -132,7 +137,7 @@ impl CaptureDevice {
         &self,
         _hmonitor: isize,
         _config: CaptureConfig,
-        _video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        _outputs: CaptureOutputs,
     ) -> Result<()> {
         Err(CaptureError::PlatformError("Not supported".to_string()))
     }
@@ -142,10 +147,10 @@ impl CaptureDevice {
         &self,
         hwnd: isize,
         config: CaptureConfig,
-        video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        outputs: CaptureOutputs,
     ) -> Result<()> {
         self.window_capture
-            .start_capture(hwnd, config, video_tx)
+            .start_capture(hwnd, config, outputs)
             .await
     }
 
@@ -154,7 +159,7 @@ impl CaptureDevice {
         &self,
         _hwnd: isize,
         _config: CaptureConfig,
-        _video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        _outputs: CaptureOutputs,
     ) -> Result<()> {
         Err(CaptureError::PlatformError("Not supported".to_string()))
     }
@@ -168,4 +173,24 @@ impl CaptureDevice {
     pub fn enumerate_audio_devices(&self) -> Result<Vec<AudioDevice>> {
         Ok(vec![])
     }
+
+    #[cfg(windows)]
+    pub fn enumerate_cameras(&self) -> Result<Vec<CameraInfo>> {
+        self::camera::enumerate_cameras()
+    }
+
+    #[cfg(not(windows))]
+    pub fn enumerate_cameras(&self) -> Result<Vec<CameraInfo>> {
+        Ok(vec![])
+    }
+
+    #[cfg(windows)]
+    pub fn start_camera(&self, device_id: Option<String>, feed: Arc<CameraFeed>) -> Result<()> {
+        self::camera::start_camera(device_id, feed)
+    }
+
+    #[cfg(not(windows))]
+    pub fn start_camera(&self, _device_id: Option<String>, _feed: Arc<CameraFeed>) -> Result<()> {
+        Err(CaptureError::PlatformError("Not supported".to_string()))
+    }
 }
@@ -1,5 +1,6 @@
 use crate::share_screen::dto::{
-    CaptureConfig, CaptureError, CaptureSourceType, MonitorInfo, MonitorRect, Result,
+    CaptureConfig, CaptureError, CaptureOutputs, CaptureSourceType, MonitorInfo, MonitorRect,
+    Result,
 };
 use windows::Win32::{
     Foundation::*, Graphics::Gdi::*, UI::WindowsAndMessaging::MONITORINFOF_PRIMARY,
@@ -86,12 +87,12 @@ impl MonitorCapture {
         &self,
         hmonitor: isize,
         config: CaptureConfig,
-        video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        outputs: CaptureOutputs,
     ) -> Result<()> {
         super::windows::start_capture_internal(
             CaptureSourceType::Monitor(hmonitor),
             config,
-            video_tx,
+            outputs,
         )
         .await
     }
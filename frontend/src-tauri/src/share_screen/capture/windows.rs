@@ -1,12 +1,26 @@
+use crate::share_screen::annotation::AnnotationFilter;
 use crate::share_screen::dto::{
-    AudioDevice, CaptureConfig, CaptureError, CaptureSourceType, Result, WindowInfo,
+    AudioDevice, CaptureConfig, CaptureError, CaptureOutputs, CaptureSourceType, ResolutionChanged,
+    Result, WindowInfo,
 };
+use crate::share_screen::events::{self, RESOLUTION_CHANGED};
+use crate::share_screen::filter::FilterChain;
+use crate::share_screen::frame::VideoFrame;
+use crate::share_screen::pacer::FramePacer;
+use crate::share_screen::pip::PipFilter;
+use crate::share_screen::queue::{CAPTURE_QUEUE, frame_queue};
+use crate::share_screen::scale::{FrameScaler, ScaleFilter, fit_size};
+use crate::share_screen::simulcast::{LayerEncoder, ThumbnailUpdater};
+use crate::share_screen::static_content::{KEEPALIVE_FPS, StaticDetector};
+use crate::share_screen::viewport::{FollowTarget, PointerSource, Viewport, ZoomFilter};
 use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use vpx_encode::{Config as VpxConfig, Encoder, VideoCodecId};
+use windows::Graphics::DirectX::DirectXPixelFormat;
 use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
-use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, HMONITOR, MONITORINFO};
 use windows::Win32::System::WinRT::Direct3D11::{
     CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
 };
@@ -83,9 +97,9 @@ impl WindowCapture {
         &self,
         hwnd: isize,
         config: CaptureConfig,
-        video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+        outputs: CaptureOutputs,
     ) -> Result<()> {
-        start_capture_internal(CaptureSourceType::Window(hwnd), config, video_tx).await
+        start_capture_internal(CaptureSourceType::Window(hwnd), config, outputs).await
     }
 }
 
@@ -312,14 +326,21 @@ pub async fn capture_single_frame_internal(
             .GetInterface()
             .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
 
-        let resized = resize_texture_gpu(&device, &context, &texture, width as u32, height as u32)?;
-        let bytes = texture_to_bytes(&context, &resized)?;
+        let frame = read_scaled_frame(
+            &device,
+            &context,
+            &texture,
+            width as u32,
+            height as u32,
+            &AtomicBool::new(true),
+            &Mutex::new(FrameScaler::new(ScaleFilter::Lanczos3)),
+        )?;
 
         session
             .Close()
             .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
 
-        Ok(bytes)
+        Ok(frame.into_packed().data)
     }
 }
 
@@ -327,7 +348,7 @@ pub async fn capture_single_frame_internal(
 pub async fn start_capture_internal(
     source_type: CaptureSourceType,
     config: CaptureConfig,
-    video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    outputs: CaptureOutputs,
 ) -> Result<()> {
     use windows::Win32::System::WinRT::*;
 
@@ -349,7 +370,7 @@ pub async fn start_capture_internal(
 
         let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
             &d3d_device,
-            windows::Graphics::DirectX::DirectXPixelFormat::B8G8R8A8UIntNormalized,
+            DirectXPixelFormat::B8G8R8A8UIntNormalized,
             2,
             size,
         )
@@ -368,32 +389,28 @@ pub async fn start_capture_internal(
             .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
 
         // Create channel for raw frames
-        let (frame_tx, frame_rx) = std::sync::mpsc::sync_channel::<(Vec<u8>, u64)>(10);
+        let (frame_tx, frame_rx) = frame_queue::<VideoFrame>(CAPTURE_QUEUE);
 
-        let target_frame_time = Duration::from_secs_f64(1.0 / config.fps as f64);
-        let last_frame_time = Arc::new(std::sync::Mutex::new(Instant::now()));
+        let last_frame_time = Arc::new(std::sync::Mutex::new(None::<Duration>));
         let frame_counter = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        // Also the frame pool size, which follows the source
+        let source_size = Arc::new(Mutex::new((size.Width as u32, size.Height as u32)));
 
         let device_clone = device.clone();
         let context_clone = context.clone();
         let last_frame_time_clone = last_frame_time.clone();
         let frame_counter_clone = frame_counter.clone();
+        let source_size_clone = source_size.clone();
         let config_clone = config.clone();
+        let control_clone = outputs.control.clone();
+        let gpu_scaler = AtomicBool::new(true);
+        let cpu_scaler = Mutex::new(FrameScaler::new(ScaleFilter::Bilinear));
 
         // Frame Arrived Handler
         frame_pool
             .FrameArrived(&TypedEventHandler::new(
                 move |pool_ref: Ref<Direct3D11CaptureFramePool>, _| {
                     let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                        // {
-                        //     let mut last_time = last_frame_time_clone.lock().unwrap();
-                        //     let now = Instant::now();
-                        //     if now.duration_since(*last_time) < target_frame_time {
-                        //         return Ok(());
-                        //     }
-                        //     *last_time = now;
-                        // }
-
                         let frame_num =
                             frame_counter_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
@@ -407,6 +424,58 @@ pub async fn start_capture_internal(
                             Err(_) => return Ok(()),
                         };
 
+                        // A resized source keeps arriving cropped into the old
+                        // pool size until the pool is recreated. The frame that
+                        // reports it still has the old layout, so it is skipped.
+                        let content_size = match frame.ContentSize() {
+                            Ok(s) => s,
+                            Err(_) => return Ok(()),
+                        };
+                        let content = (content_size.Width as u32, content_size.Height as u32);
+                        let (source_width, source_height) = {
+                            let mut source = source_size_clone.lock().unwrap();
+                            if content.0 > 0 && content.1 > 0 && content != *source {
+                                let recreated = create_winrt_device(&device_clone).and_then(|d| {
+                                    pool.Recreate(
+                                        &d,
+                                        DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                                        2,
+                                        content_size,
+                                    )
+                                    .map_err(|e| CaptureError::PlatformError(e.to_string()))
+                                });
+                                if let Err(e) = recreated {
+                                    eprintln!("[Capture] Failed to resize frame pool: {:?}", e);
+                                } else {
+                                    println!(
+                                        "[Capture] Source resized to {}x{}",
+                                        content.0, content.1
+                                    );
+                                    *source = content;
+                                }
+                                return Ok(());
+                            }
+                            *source
+                        };
+
+                        let timestamp = match frame.SystemRelativeTime() {
+                            Ok(t) => Duration::from_nanos(t.Duration.max(0) as u64 * 100),
+                            Err(_) => return Ok(()),
+                        };
+
+                        // Skip frames the pacer would discard anyway, before the
+                        // GPU readback. Some slack absorbs capture jitter.
+                        {
+                            let interval = Duration::from_secs(1) / control_clone.target_fps();
+                            let mut last_time = last_frame_time_clone.lock().unwrap();
+                            if let Some(last) = *last_time
+                                && timestamp.saturating_sub(last) < interval * 9 / 10
+                            {
+                                return Ok(());
+                            }
+                            *last_time = Some(timestamp);
+                        }
+
                         let surface = match frame.Surface() {
                             Ok(s) => s,
                             Err(_) => return Ok(()),
@@ -422,34 +491,38 @@ pub async fn start_capture_internal(
                             Err(_) => return Ok(()),
                         };
 
-                        let resized = match resize_texture_gpu(
+                        // Fit in the configured size without stretching. A zoom
+                        // crops at source size and scales only what it shows.
+                        let (width, height) = if config_clone.zoom.is_some() {
+                            (source_width, source_height)
+                        } else {
+                            fit_size(
+                                source_width,
+                                source_height,
+                                config_clone.width as u32,
+                                config_clone.height as u32,
+                            )
+                        };
+                        let video_frame = match read_scaled_frame(
                             &device_clone,
                             &context_clone,
                             &texture,
-                            config_clone.width as u32,
-                            config_clone.height as u32,
+                            width,
+                            height,
+                            &gpu_scaler,
+                            &cpu_scaler,
                         ) {
-                            Ok(r) => r,
+                            Ok(f) => f.with_timing(timestamp, frame_num),
                             Err(e) => {
                                 if frame_num % 30 == 0 {
-                                    eprintln!("[Capture] Resize error: {:?}", e);
-                                }
-                                return Ok(());
-                            }
-                        };
-
-                        let bgra_bytes = match texture_to_bytes(&context_clone, &resized) {
-                            Ok(b) => b,
-                            Err(e) => {
-                                if frame_num % 30 == 0 {
-                                    eprintln!("[Capture] Texture read error: {:?}", e);
+                                    eprintln!("[Capture] Frame read error: {:?}", e);
                                 }
                                 return Ok(());
                             }
                         };
 
                         // Send raw frame to encoder task
-                        let _ = frame_tx.try_send((bgra_bytes, frame_num));
+                        let _ = frame_tx.send(video_frame);
 
                         Ok(())
                     }));
@@ -466,66 +539,289 @@ pub async fn start_capture_internal(
             .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
 
         // Encoder Task
-        let encoder_config = VpxConfig {
-            width: config.width as u32,
-            height: config.height as u32,
-            timebase: [1, config.fps as i32],
-            bitrate: 1000,
-            codec: VideoCodecId::VP8,
-        };
-
-        let width = config.width as usize;
-        let height = config.height as usize;
+        let color_space = config.color_space;
+        let stop = outputs.stop.clone();
+
+        let encoder = tokio::task::spawn_blocking(move || {
+            let control = outputs.control.clone();
+
+            // Overlays depend on the source size, so both are rebuilt when it
+            // changes. The layer sinks, and with them the tracks, are kept.
+            let build_filters = |(source_width, source_height): (u32, u32)| {
+                let mut filters = FilterChain::from_config(&config.filters, config.color_space);
+                if let Some(zoom) = &config.zoom {
+                    filters.prepend(Box::new(ZoomFilter::new(
+                        Viewport::new(zoom.clone(), source_width, source_height),
+                        pointer_source(source_type.clone(), zoom.follow),
+                        fit_size(
+                            source_width,
+                            source_height,
+                            config.width as u32,
+                            config.height as u32,
+                        ),
+                    )));
+                }
+                // Annotations are in source coordinates, so they go before the zoom
+                filters.prepend(Box::new(AnnotationFilter::new(
+                    outputs.annotations.clone(),
+                    source_width,
+                    source_height,
+                )));
+                if let (Some(pip), Some(feed)) = (&config.pip, &outputs.camera) {
+                    filters.push(Box::new(PipFilter::new(pip.clone(), feed.clone())));
+                }
+                filters
+            };
+            let layer_thumbnail =
+                |filters: &FilterChain| config.simulcast && filters.outputs_bgra();
+            let build_layers =
+                |filters: &FilterChain, (source_width, source_height): (u32, u32)| {
+                    let (width, height) = fit_size(
+                        source_width,
+                        source_height,
+                        config.width as u32,
+                        config.height as u32,
+                    );
+                    // Layers encode what the filters produce, not the capture size
+                    let (width, height) = filters.output_size(width, height);
+                    let layer_config = CaptureConfig {
+                        width: width as i32,
+                        height: height as i32,
+                        ..config.clone()
+                    };
 
-        tokio::task::spawn_blocking(move || {
-            let mut encoder = match Encoder::new(encoder_config) {
-                Ok(e) => e,
-                Err(e) => {
-                    eprintln!("[Encode] ✗ Failed to create VP8 encoder: {:?}", e);
+                    let layers = outputs
+                        .layers
+                        .iter()
+                        .cloned()
+                        .map(|sink| LayerEncoder::new(sink, &layer_config, &control))
+                        .collect::<Result<Vec<_>>>()
+                        .map(|mut layers| {
+                            // The smallest layer is already at thumbnail size
+                            if layer_thumbnail(filters)
+                                && let Some(smallest) = layers.pop()
+                            {
+                                layers.push(smallest.with_thumbnail(outputs.thumbnail.clone()));
+                            }
+                            layers
+                        });
+                    (layers, (width, height))
+                };
+
+            let mut current_source = *source_size.lock().unwrap();
+            let mut filters = build_filters(current_source);
+            let mut layers = match build_layers(&filters, current_source) {
+                (Ok(layers), _) => layers,
+                (Err(e), _) => {
+                    eprintln!(
+                        "[Encode] ✗ Failed to create {:?} encoder: {:?}",
+                        config.codec, e
+                    );
                     return;
                 }
             };
 
-            loop {
-                match frame_rx.recv() {
-                    Ok((bgra_bytes, frame_num)) => {
-                        let i420_data = bgra_to_i420(&bgra_bytes, width, height);
-
-                        match encoder.encode(frame_num as i64, &i420_data) {
-                            Ok(packets) => {
-                                for packet in packets {
-                                    let data = packet.data.to_vec();
+            let mut pacer = FramePacer::new(outputs.epoch, control.target_fps());
+            let mut detector = StaticDetector::default();
+            let mut scaler = FrameScaler::new(ScaleFilter::Bilinear);
+            let mut thumbnail = ThumbnailUpdater::new(outputs.thumbnail.clone());
+            let mut pending: Option<VideoFrame> = None;
+            let mut last_captured: Option<VideoFrame> = None;
+            let mut seen_revision = filters.revision();
+            let mut sequence = 0u64;
+
+            'encode: loop {
+                // Frames still queued belong to a source that was switched away
+                if outputs.stop.load(Ordering::Relaxed) {
+                    break;
+                }
 
-                                    video_tx.blocking_send(data).unwrap();
+                let captured = match frame_rx.recv_timeout(pacer.time_until_tick(Instant::now())) {
+                    Ok(frame) => {
+                        let source = *source_size.lock().unwrap();
+                        if source != current_source {
+                            filters = build_filters(source);
+                            match build_layers(&filters, source) {
+                                (Ok(resized), (width, height)) => {
+                                    layers = resized;
+                                    println!("[Encode] Encoding at {}x{}", width, height);
+                                    events::emit(
+                                        RESOLUTION_CHANGED,
+                                        ResolutionChanged {
+                                            id: source_type.to_id(),
+                                            source_width: source.0,
+                                            source_height: source.1,
+                                            width,
+                                            height,
+                                        },
+                                    );
                                 }
-                            }
-                            Err(e) => {
-                                if frame_num % 30 == 0 {
-                                    eprintln!("[Encode] VP8 encode error: {:?}", e);
+                                (Err(e), _) => {
+                                    eprintln!(
+                                        "[Encode] ✗ Failed to resize {:?} encoder: {:?}",
+                                        config.codec, e
+                                    );
+                                    break;
                                 }
                             }
+                            current_source = source;
+                            seen_revision = filters.revision();
+                            pending = None;
+                        }
+
+                        if !layer_thumbnail(&filters)
+                            && let Err(e) = thumbnail.update(&frame, Instant::now())
+                        {
+                            eprintln!("[Encode] Thumbnail error: {:?}", e);
                         }
+                        last_captured = Some(frame.clone());
+                        Some(frame)
                     }
-                    Err(e) => {
-                        eprintln!("[Encode] Channel closed: {:?}", e);
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        eprintln!("[Encode] Channel closed");
                         break;
                     }
+                };
+
+                // Capture is silent on a static screen, so overlay changes
+                // re-filter the last captured frame
+                let revision = filters.revision();
+                let captured = match captured {
+                    None if revision != seen_revision => last_captured.clone(),
+                    captured => captured,
+                };
+                seen_revision = revision;
+
+                // Unchanged frames are dropped, and of the rest only the
+                // newest is encoded
+                if let Some(frame) = captured {
+                    match filters.apply(frame) {
+                        Ok(frame) => {
+                            let was_idle = detector.is_idle();
+                            if detector.dirty_tiles(&frame) > 0 {
+                                if was_idle {
+                                    pacer.wake(Instant::now());
+                                }
+                                pending = Some(frame);
+                            }
+                        }
+                        Err(e) => eprintln!("[Encode] Filter error: {:?}", e),
+                    }
+                }
+
+                // Static content only needs a keep-alive trickle
+                if detector.is_idle() {
+                    pacer.set_fps(KEEPALIVE_FPS.min(control.target_fps()));
+                } else {
+                    pacer.set_fps(control.target_fps());
+                }
+                let Some(timestamp) = pacer.poll(Instant::now()) else {
+                    continue;
+                };
+                // Paused by the viewers, the newest frame waits in `pending`
+                if control.is_paused() {
+                    continue;
+                }
+
+                // One capture feeds every layer
+                if let Some(frame) = pending.take() {
+                    for layer in layers.iter_mut() {
+                        if let Err(e) = layer.update(&frame, &mut scaler, color_space) {
+                            eprintln!("[Encode] Frame preparation error: {:?}", e);
+                        }
+                    }
+                }
+
+                let keyframe = control.take_keyframe_request();
+                for layer in layers.iter_mut() {
+                    if layer
+                        .encode(timestamp, sequence, keyframe, &control)
+                        .is_err()
+                    {
+                        println!("[Encode] Preview track closed");
+                        break 'encode;
+                    }
                 }
+                sequence += 1;
             }
 
-            println!("[Encode] Encoder task ended");
+            let captured = frame_rx.stats();
+            println!(
+                "[Encode] Encoder task ended, dropped {}/{} captured frames",
+                captured.dropped, captured.queued
+            );
+            for layer in &layers {
+                let encoded = layer.sink().video_tx.stats();
+                println!(
+                    "[Encode] Layer {} dropped {}/{} encoded frames",
+                    layer.sink().layer.rid,
+                    encoded.dropped,
+                    encoded.queued
+                );
+            }
         });
 
         session
             .StartCapture()
             .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
 
-        loop {
+        // The encoder task ends on the same flag, and drops what is queued
+        while !stop.load(Ordering::Relaxed) {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
+
+        session
+            .Close()
+            .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        println!("[Capture] Capture session closed");
+
+        // Nothing of this session reaches the encoded queues after return
+        let _ = encoder.await;
+
+        Ok(())
     }
 }
 
+// Zoom target relative to the captured source, in source pixels
+fn pointer_source(source_type: CaptureSourceType, follow: FollowTarget) -> PointerSource {
+    Box::new(move || unsafe {
+        let origin = match source_type {
+            CaptureSourceType::Monitor(hmonitor) => {
+                let mut info = MONITORINFO {
+                    cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                    ..Default::default()
+                };
+                if !GetMonitorInfoW(HMONITOR(hmonitor as *mut _), &mut info).as_bool() {
+                    return None;
+                }
+                info.rcMonitor
+            }
+            CaptureSourceType::Window(hwnd) => {
+                let mut rect = RECT::default();
+                GetWindowRect(HWND(hwnd as *mut _), &mut rect).ok()?;
+                rect
+            }
+        };
+
+        let (x, y) = match follow {
+            FollowTarget::Cursor => {
+                let mut point = POINT::default();
+                GetCursorPos(&mut point).ok()?;
+                (point.x, point.y)
+            }
+            FollowTarget::FocusedWindow => {
+                let mut rect = RECT::default();
+                GetWindowRect(GetForegroundWindow(), &mut rect).ok()?;
+                ((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2)
+            }
+        };
+
+        let inside = x >= origin.left && x < origin.right && y >= origin.top && y < origin.bottom;
+        inside.then(|| ((x - origin.left) as f32, (y - origin.top) as f32))
+    })
+}
+
 unsafe fn create_d3d11_device() -> Result<(ID3D11Device, ID3D11DeviceContext)> {
     unsafe {
         let mut device: Option<ID3D11Device> = None;
@@ -710,10 +1006,53 @@ unsafe fn resize_texture_gpu(
     }
 }
 
-unsafe fn texture_to_bytes(
+// Scales on the GPU video processor when the driver exposes one, otherwise
+// reads back the full-size texture and scales it on the CPU. The first GPU
+// failure disables the GPU path for the rest of the session.
+unsafe fn read_scaled_frame(
+    device: &ID3D11Device,
     context: &ID3D11DeviceContext,
     texture: &ID3D11Texture2D,
-) -> Result<Vec<u8>> {
+    width: u32,
+    height: u32,
+    gpu_scaler: &AtomicBool,
+    cpu_scaler: &Mutex<FrameScaler>,
+) -> Result<VideoFrame> {
+    unsafe {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        texture.GetDesc(&mut desc);
+        if (desc.Width, desc.Height) == (width, height) {
+            return texture_to_frame(context, texture);
+        }
+
+        if gpu_scaler.load(Ordering::Relaxed) {
+            match resize_texture_gpu(device, context, texture, width, height) {
+                Ok(resized) => return texture_to_frame(context, &resized),
+                Err(e) => {
+                    eprintln!(
+                        "[Capture] GPU scaler unavailable, falling back to CPU: {:?}",
+                        e
+                    );
+                    gpu_scaler.store(false, Ordering::Relaxed);
+                }
+            }
+        }
+
+        let frame = texture_to_frame(context, texture)?;
+
+        cpu_scaler
+            .lock()
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?
+            .scale_frame(&frame, width, height)
+    }
+}
+
+// Reads a BGRA texture back to the CPU. Rows keep the driver's `RowPitch`
+// padding, which is recorded as the frame stride.
+unsafe fn texture_to_frame(
+    context: &ID3D11DeviceContext,
+    texture: &ID3D11Texture2D,
+) -> Result<VideoFrame> {
     unsafe {
         let mut desc = D3D11_TEXTURE2D_DESC::default();
         texture.GetDesc(&mut desc);
@@ -747,43 +1086,6 @@ unsafe fn texture_to_bytes(
 
         context.Unmap(&staging, 0);
 
-        Ok(result)
+        VideoFrame::bgra(desc.Width, desc.Height, mapped.RowPitch as usize, result)
     }
 }
-
-// Convert BGRA to I420 (YUV420p)
-fn bgra_to_i420(bgra: &[u8], width: usize, height: usize) -> Vec<u8> {
-    let y_size = width * height;
-    let u_size = y_size / 4;
-    let v_size = y_size / 4;
-
-    let mut i420 = vec![0u8; y_size + u_size + v_size];
-
-    // Safe non-overlapping mutable slices
-    let (y_plane, uv) = i420.split_at_mut(y_size);
-    let (u_plane, v_plane) = uv.split_at_mut(u_size);
-
-    for y in 0..height {
-        for x in 0..width {
-            let bgra_idx = (y * width + x) * 4;
-            let b = bgra[bgra_idx] as f32;
-            let g = bgra[bgra_idx + 1] as f32;
-            let r = bgra[bgra_idx + 2] as f32;
-
-            let y_val = (0.299 * r + 0.587 * g + 0.114 * b) as u8;
-            y_plane[y * width + x] = y_val;
-
-            if y % 2 == 0 && x % 2 == 0 {
-                let uv_idx = (y / 2) * (width / 2) + (x / 2);
-
-                let u_val = (-0.147 * r - 0.289 * g + 0.436 * b + 128.0) as u8;
-                u_plane[uv_idx] = u_val;
-
-                let v_val = (0.615 * r - 0.515 * g - 0.100 * b + 128.0) as u8;
-                v_plane[uv_idx] = v_val;
-            }
-        }
-    }
-
-    i420
-}
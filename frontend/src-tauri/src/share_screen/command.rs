@@ -1,6 +1,19 @@
 // src/share_screen/command.rs
-use crate::share_screen::{MANAGER, WEBRTC_SERVER, dto::*};
+use crate::share_screen::{
+    MANAGER, WEBRTC_SERVER,
+    annotation::{AnnotationEvent, Shape},
+    control::{self, ControlMessage},
+    dto::*,
+    encoder::{VideoCodec, tuning::EncoderTuning},
+    filter::FilterConfig,
+    pip::PipConfig,
+    remote::{self, RevokeReason},
+    settings::{WebRTCSettings, settings_path},
+    stats::{self, StreamStats},
+    viewport::ZoomConfig,
+};
 use anyhow::Result;
+use tauri::AppHandle;
 
 #[tauri::command]
 pub async fn get_monitors(fps: i32, width: i32, height: i32) -> Result<Vec<CaptureSource>, String> {
@@ -93,17 +106,39 @@ pub async fn get_window_by_id(
     }
 }
 
+#[tauri::command]
+pub async fn get_cameras() -> Result<Vec<CameraInfo>, String> {
+    MANAGER
+        .read()
+        .await
+        .get_cameras()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn start_monitor_preview(
     hmonitor: isize,
     fps: i32,
     width: i32,
     height: i32,
+    codec: Option<VideoCodec>,
+    simulcast: Option<bool>,
+    encoder: Option<EncoderTuning>,
+    filters: Option<Vec<FilterConfig>>,
+    pip: Option<PipConfig>,
+    zoom: Option<ZoomConfig>,
 ) -> Result<(), String> {
     let config = CaptureConfig {
         fps,
         width,
         height,
+        codec: codec.unwrap_or_default(),
+        simulcast: simulcast.unwrap_or_default(),
+        encoder: encoder.unwrap_or_default(),
+        filters: filters.unwrap_or_default(),
+        pip,
+        zoom,
         ..Default::default()
     };
 
@@ -121,11 +156,23 @@ pub async fn start_window_preview(
     fps: i32,
     width: i32,
     height: i32,
+    codec: Option<VideoCodec>,
+    simulcast: Option<bool>,
+    encoder: Option<EncoderTuning>,
+    filters: Option<Vec<FilterConfig>>,
+    pip: Option<PipConfig>,
+    zoom: Option<ZoomConfig>,
 ) -> Result<(), String> {
     let config = CaptureConfig {
         fps,
         width,
         height,
+        codec: codec.unwrap_or_default(),
+        simulcast: simulcast.unwrap_or_default(),
+        encoder: encoder.unwrap_or_default(),
+        filters: filters.unwrap_or_default(),
+        pip,
+        zoom,
         ..Default::default()
     };
 
@@ -137,6 +184,31 @@ pub async fn start_window_preview(
         .map_err(|e| e.to_string())
 }
 
+/// Shares `source_id` on the running preview `id` without a new offer.
+#[tauri::command]
+pub async fn switch_preview_source(id: String, source_id: String) -> Result<(), String> {
+    let source_type =
+        CaptureSourceType::from_id(&source_id).ok_or_else(|| "Invalid source ID".to_string())?;
+
+    let old_capture = MANAGER
+        .write()
+        .await
+        .begin_switch(&id, &source_type)
+        .map_err(|e| e.to_string())?;
+    let Some(old_capture) = old_capture else {
+        return Ok(());
+    };
+
+    // Other commands go on while the old capture drains
+    let _ = old_capture.await;
+
+    MANAGER
+        .write()
+        .await
+        .switch_source(&id, source_type)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn stop_preview(id: String) -> Result<(), String> {
     MANAGER
@@ -147,6 +219,64 @@ pub async fn stop_preview(id: String) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn add_annotation(id: String, shape: Shape) -> Result<(), String> {
+    control::apply_annotation(&id, None, &AnnotationEvent::Add { shape })
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn undo_annotation(id: String) -> Result<bool, String> {
+    control::apply_annotation(&id, None, &AnnotationEvent::Undo)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_annotations(id: String) -> Result<(), String> {
+    control::apply_annotation(&id, None, &AnnotationEvent::Clear)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Lets the preview's viewers annotate over the control channel. Off until
+/// the sharer turns it on.
+#[tauri::command]
+pub async fn allow_viewer_annotations(id: String, allowed: bool) -> Result<(), String> {
+    MANAGER
+        .read()
+        .await
+        .annotations(&id)
+        .map(|layer| layer.set_viewers_allowed(allowed))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_stream_stats(id: String) -> Result<StreamStats, String> {
+    stats::latest(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_webrtc_settings() -> Result<WebRTCSettings, String> {
+    Ok(WEBRTC_SERVER.read().await.settings().clone())
+}
+
+#[tauri::command]
+pub async fn set_webrtc_settings(app: AppHandle, settings: WebRTCSettings) -> Result<(), String> {
+    WEBRTC_SERVER
+        .write()
+        .await
+        .configure(settings.clone())
+        .map_err(|e| e.to_string())?;
+
+    settings_path(&app)
+        .and_then(|path| settings.save(&path))
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_preview_offer(id: String) -> Result<PreviewOffer, String> {
     WEBRTC_SERVER
@@ -158,26 +288,97 @@ pub async fn get_preview_offer(id: String) -> Result<PreviewOffer, String> {
 }
 
 #[tauri::command]
-pub async fn accept_preview_answer(id: String, sdp: String) -> Result<(), String> {
+pub async fn accept_preview_answer(
+    id: String,
+    viewer_id: String,
+    sdp: String,
+) -> Result<(), String> {
+    WEBRTC_SERVER
+        .read()
+        .await
+        .accept_preview_answer(&id, &viewer_id, sdp)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Answerer mode: the viewer sends its own offer (receiving video) and
+/// gets Rust's answer. ICE candidates are exchanged as with `get_preview_offer`.
+#[tauri::command]
+pub async fn answer_preview_offer(id: String, sdp: String) -> Result<PreviewAnswer, String> {
     WEBRTC_SERVER
         .write()
         .await
-        .accept_preview_answer(&id, sdp)
+        .answer_preview_offer(&id, sdp)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sends a control channel message to one viewer, or to all of them
+/// without `viewer_id`.
+#[tauri::command]
+pub async fn send_preview_control(
+    id: String,
+    viewer_id: Option<String>,
+    message: ControlMessage,
+) -> Result<(), String> {
+    let server = WEBRTC_SERVER.read().await;
+    match viewer_id {
+        Some(viewer_id) => server
+            .send_control(&id, &viewer_id, &message)
+            .await
+            .map_err(|e| e.to_string()),
+        None => {
+            server.broadcast_control(&id, None, &message).await;
+            Ok(())
+        }
+    }
+}
+
+/// The sharer's consent to a viewer's `request_control`, shown to them on
+/// the `REMOTE_CONTROL` event. Only lasts until revoked.
+#[tauri::command]
+pub async fn grant_remote_control(id: String, viewer_id: String) -> Result<(), String> {
+    remote::grant(&id, &viewer_id)
         .await
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn deny_remote_control(id: String, viewer_id: String) -> Result<(), String> {
+    remote::deny(&id, &viewer_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn revoke_remote_control() -> Result<(), String> {
+    remote::revoke(RevokeReason::Sharer);
+    Ok(())
+}
+
+/// Disconnects one viewer. The preview keeps running for the others, and
+/// until `stop_preview` when this was the last one.
+#[tauri::command]
+pub async fn close_preview_viewer(id: String, viewer_id: String) -> Result<(), String> {
+    WEBRTC_SERVER
+        .read()
+        .await
+        .close_viewer(&id, &viewer_id)
+        .await
+        .map(|_| ())
+        .ok_or_else(|| format!("No viewer {} on {}", viewer_id, id))
+}
+
 #[tauri::command]
 pub async fn add_preview_ice_candidate(
     id: String,
+    viewer_id: String,
     candidate: String,
     sdp_mid: Option<String>,
     sdp_mline_index: Option<u16>,
 ) -> Result<(), String> {
     WEBRTC_SERVER
-        .write()
+        .read()
         .await
-        .add_preview_ice_candidate(id, candidate, sdp_mid, sdp_mline_index)
+        .add_preview_ice_candidate(id, viewer_id, candidate, sdp_mid, sdp_mline_index)
         .await
         .map_err(|e| e.to_string())
 }
@@ -0,0 +1,197 @@
+// src/share_screen/control.rs
+use crate::share_screen::annotation::{AnnotationEvent, Point};
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::events::{self, CONTROL_MESSAGE};
+use crate::share_screen::remote::{self, RemoteControlChange, RemoteInput, RevokeReason};
+use crate::share_screen::stats::ViewerReport;
+use crate::share_screen::{MANAGER, WEBRTC_SERVER};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::data_channel_state::RTCDataChannelState;
+
+/// Label of the reliable data channel on every viewer's peer connection.
+pub const CONTROL_CHANNEL: &str = "control";
+
+/// Messages between the sharer and a viewer, one JSON text message each.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// The viewer stopped watching for now. Encoding pauses once every
+    /// viewer has.
+    Pause,
+    Resume,
+    RequestKeyframe,
+    /// Pointer in source pixels, unset when it left the source.
+    Cursor {
+        position: Option<Point>,
+    },
+    /// From a viewer, only applied while the sharer allows viewer
+    /// annotations.
+    Annotation {
+        event: AnnotationEvent,
+    },
+    Stats(ViewerReport),
+    /// The viewer asks the sharer for control of the source.
+    RequestControl,
+    /// The viewer gives control back.
+    ReleaseControl,
+    /// Only injected while the viewer has control.
+    Input {
+        event: RemoteInput,
+    },
+    /// The sharer's answer to `RequestControl`, and the end of control.
+    RemoteControl(RemoteControlChange),
+}
+
+impl ControlMessage {
+    /// Whether a viewer may send this. The rest only goes to viewers.
+    pub fn viewer_may_send(&self) -> bool {
+        match self {
+            ControlMessage::Pause
+            | ControlMessage::Resume
+            | ControlMessage::RequestKeyframe
+            | ControlMessage::Cursor { .. }
+            | ControlMessage::Annotation { .. }
+            | ControlMessage::Stats(_)
+            | ControlMessage::RequestControl
+            | ControlMessage::ReleaseControl
+            | ControlMessage::Input { .. } => true,
+            ControlMessage::RemoteControl(_) => false,
+        }
+    }
+}
+
+/// Payload of `CONTROL_MESSAGE`, the messages a viewer sends apart from
+/// remote control.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewControlMessage {
+    pub id: String,
+    pub viewer_id: String,
+    #[serde(flatten)]
+    pub message: ControlMessage,
+}
+
+/// A viewer's end of the control channel. Empty until the channel exists,
+/// which in answerer mode is when the viewer opens it.
+#[derive(Default)]
+pub struct ControlChannel {
+    channel: Mutex<Option<Arc<RTCDataChannel>>>,
+}
+
+impl ControlChannel {
+    pub fn attach(&self, id: &str, viewer_id: &str, channel: Arc<RTCDataChannel>) {
+        let (id, viewer_id) = (id.to_string(), viewer_id.to_string());
+        channel.on_message(Box::new(move |msg: DataChannelMessage| {
+            match serde_json::from_slice::<ControlMessage>(&msg.data) {
+                // Right here, input has to stay in order
+                Ok(ControlMessage::Input { event }) => remote::input(&id, &viewer_id, &event),
+                // Would pass for the sharer's own decisions in CONTROL_MESSAGE
+                Ok(message) if !message.viewer_may_send() => {
+                    eprintln!("[Control] {} dropped {:?} from {}", id, message, viewer_id);
+                }
+                // Handled on its own task, handlers never wait on the server locks
+                Ok(message) => {
+                    tokio::spawn(handle_message(id.clone(), viewer_id.clone(), message));
+                }
+                Err(e) => eprintln!("[Control] {} bad message from {}: {}", id, viewer_id, e),
+            }
+            Box::pin(async {})
+        }));
+
+        *self.channel.lock().unwrap() = Some(channel);
+    }
+
+    pub async fn send(&self, message: &ControlMessage) -> Result<()> {
+        let channel = self
+            .channel
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|channel| channel.ready_state() == RTCDataChannelState::Open)
+            .ok_or_else(|| CaptureError::WebRTCError("Control channel is not open".to_string()))?;
+
+        let text = serde_json::to_string(message)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+        channel
+            .send_text(text)
+            .await
+            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+async fn handle_message(id: String, viewer_id: String, message: ControlMessage) {
+    // Remote control has its own event
+    match message {
+        ControlMessage::RequestControl => return remote::request(&id, &viewer_id),
+        ControlMessage::ReleaseControl => {
+            return remote::end_session(&id, Some(&viewer_id), RevokeReason::Released);
+        }
+        _ => {}
+    }
+
+    let result = match &message {
+        ControlMessage::Annotation { event } => apply_annotation(&id, Some(&viewer_id), event)
+            .await
+            .map(|_| ()),
+        _ => {
+            WEBRTC_SERVER
+                .read()
+                .await
+                .on_control_message(&id, &viewer_id, &message)
+                .await
+        }
+    };
+
+    match result {
+        Ok(()) => events::emit(
+            CONTROL_MESSAGE,
+            PreviewControlMessage {
+                id,
+                viewer_id,
+                message,
+            },
+        ),
+        Err(e) => eprintln!("[Control] {} {} failed: {:?}", id, viewer_id, e),
+    }
+}
+
+/// Applies an annotation edit to the stream and passes it on to its
+/// viewers, except `from` who made it. Edits from viewers need the sharer's
+/// permission. Returns false when nothing changed.
+pub async fn apply_annotation(
+    id: &str,
+    from: Option<&str>,
+    event: &AnnotationEvent,
+) -> Result<bool> {
+    let layer = MANAGER.read().await.annotations(id)?;
+    if let Some(viewer_id) = from
+        && !layer.viewers_allowed()
+    {
+        return Err(CaptureError::PermissionDenied(format!(
+            "{} may not annotate {}",
+            viewer_id, id
+        )));
+    }
+
+    if !event.apply(&layer) {
+        return Ok(false);
+    }
+
+    WEBRTC_SERVER
+        .read()
+        .await
+        .broadcast_control(
+            id,
+            from,
+            &ControlMessage::Annotation {
+                event: event.clone(),
+            },
+        )
+        .await;
+
+    Ok(true)
+}
@@ -0,0 +1,284 @@
+// src/share_screen/convert.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{ColorMatrix, ColorRange, ColorSpace, PixelFormat, VideoFrame};
+use rayon::prelude::*;
+
+const SHIFT: u32 = 16;
+const ONE: i32 = 1 << SHIFT;
+
+/// RGB to YCbCr weights in 16.16 fixed point for one matrix/range pair.
+struct Coefficients {
+    y: [i32; 3],
+    u: [i32; 3],
+    v: [i32; 3],
+    y_offset: i32,
+}
+
+impl Coefficients {
+    fn new(color_space: ColorSpace) -> Self {
+        let (kr, kb) = match color_space.matrix {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+        };
+        let kg = 1.0 - kr - kb;
+
+        let (y_scale, c_scale, y_offset) = match color_space.range {
+            ColorRange::Limited => (219.0 / 255.0, 224.0 / 255.0, 16),
+            ColorRange::Full => (1.0, 1.0, 0),
+        };
+
+        let fixed = |v: f64| (v * ONE as f64).round() as i32;
+        let cb = c_scale / (2.0 * (1.0 - kb));
+        let cr = c_scale / (2.0 * (1.0 - kr));
+
+        Self {
+            y: [
+                fixed(kr * y_scale),
+                fixed(kg * y_scale),
+                fixed(kb * y_scale),
+            ],
+            u: [fixed(-kr * cb), fixed(-kg * cb), fixed((1.0 - kb) * cb)],
+            v: [fixed((1.0 - kr) * cr), fixed(-kg * cr), fixed(-kb * cr)],
+            y_offset,
+        }
+    }
+
+    fn luma(&self, r: i32, g: i32, b: i32) -> u8 {
+        let y = (self.y[0] * r + self.y[1] * g + self.y[2] * b + ONE / 2) >> SHIFT;
+        (y + self.y_offset).clamp(0, 255) as u8
+    }
+
+    /// Chroma of the average of `count` pixels whose channels sum to r/g/b.
+    fn chroma(&self, r: i32, g: i32, b: i32, count: i32) -> (u8, u8) {
+        let divisor = count * ONE;
+        let round = |sum: i32| ((sum + divisor / 2).div_euclid(divisor) + 128).clamp(0, 255) as u8;
+
+        (
+            round(self.u[0] * r + self.u[1] * g + self.u[2] * b),
+            round(self.v[0] * r + self.v[1] * g + self.v[2] * b),
+        )
+    }
+}
+
+/// Converts a BGRA frame to tightly packed I420.
+///
+/// Chroma is the box filtered average of each 2x2 block. On odd widths and
+/// heights the last column/row of blocks averages only the pixels that exist.
+/// Row pairs are converted in parallel.
+pub fn bgra_to_i420(frame: &VideoFrame, color_space: ColorSpace) -> Result<VideoFrame> {
+    if frame.format != PixelFormat::Bgra {
+        return Err(CaptureError::ProcessingError(format!(
+            "Expected a BGRA frame, got {:?}",
+            frame.format
+        )));
+    }
+
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let chroma_width = width.div_ceil(2);
+    let chroma_height = height.div_ceil(2);
+    let coefficients = Coefficients::new(color_space);
+
+    let mut data = vec![0u8; width * height + 2 * chroma_width * chroma_height];
+    let (y_plane, uv) = data.split_at_mut(width * height);
+    let (u_plane, v_plane) = uv.split_at_mut(chroma_width * chroma_height);
+
+    if width > 0 && height > 0 {
+        y_plane
+            .par_chunks_mut(width * 2)
+            .zip(u_plane.par_chunks_mut(chroma_width))
+            .zip(v_plane.par_chunks_mut(chroma_width))
+            .enumerate()
+            .for_each(|(pair, ((y_rows, u_row), v_row))| {
+                let rows: Vec<&[u8]> = (pair * 2..(pair * 2 + 2).min(height))
+                    .map(|y| frame.row(0, y))
+                    .collect();
+
+                for (y_row, src) in y_rows.chunks_mut(width).zip(&rows) {
+                    for (luma, px) in y_row.iter_mut().zip(src.chunks_exact(4)) {
+                        *luma = coefficients.luma(px[2] as i32, px[1] as i32, px[0] as i32);
+                    }
+                }
+
+                for (cx, (u, v)) in u_row.iter_mut().zip(v_row.iter_mut()).enumerate() {
+                    let (mut r, mut g, mut b, mut count) = (0, 0, 0, 0);
+
+                    for src in &rows {
+                        let block = &src[cx * 8..(cx * 8 + 8).min(src.len())];
+                        for px in block.chunks_exact(4) {
+                            b += px[0] as i32;
+                            g += px[1] as i32;
+                            r += px[2] as i32;
+                            count += 1;
+                        }
+                    }
+
+                    (*u, *v) = coefficients.chroma(r, g, b, count);
+                }
+            });
+    }
+
+    Ok(VideoFrame::i420(frame.width, frame.height, data)?
+        .with_timing(frame.timestamp, frame.frame_number)
+        .with_color_space(color_space))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Inputs as (R, G, B): white, black, red, green, blue, mid grey
+    const COLORS: [[u8; 3]; 6] = [
+        [255, 255, 255],
+        [0, 0, 0],
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [128, 128, 128],
+    ];
+
+    // Reference (Y, U, V) for `COLORS`, from the BT.601/BT.709 equations in
+    // floating point, rounded half up
+    const BT601_LIMITED: [[u8; 3]; 6] = [
+        [235, 128, 128],
+        [16, 128, 128],
+        [81, 90, 240],
+        [145, 54, 34],
+        [41, 240, 110],
+        [126, 128, 128],
+    ];
+    const BT601_FULL: [[u8; 3]; 6] = [
+        [255, 128, 128],
+        [0, 128, 128],
+        [76, 85, 255],
+        [150, 44, 21],
+        [29, 255, 107],
+        [128, 128, 128],
+    ];
+    const BT709_LIMITED: [[u8; 3]; 6] = [
+        [235, 128, 128],
+        [16, 128, 128],
+        [63, 102, 240],
+        [173, 42, 26],
+        [32, 240, 118],
+        [126, 128, 128],
+    ];
+    const BT709_FULL: [[u8; 3]; 6] = [
+        [255, 128, 128],
+        [0, 128, 128],
+        [54, 99, 255],
+        [182, 30, 12],
+        [18, 255, 116],
+        [128, 128, 128],
+    ];
+
+    const WHITE: [u8; 3] = [255, 255, 255];
+    const RED: [u8; 3] = [255, 0, 0];
+
+    fn space(matrix: ColorMatrix, range: ColorRange) -> ColorSpace {
+        ColorSpace { matrix, range }
+    }
+
+    // BGRA frame from RGB pixels, with `padding` junk bytes after each row
+    fn bgra(width: usize, height: usize, padding: usize, pixels: &[[u8; 3]]) -> VideoFrame {
+        let stride = width * 4 + padding;
+        let mut data = vec![0xA5; stride * height];
+
+        for (i, [r, g, b]) in pixels.iter().enumerate() {
+            let start = (i / width) * stride + (i % width) * 4;
+            data[start..start + 4].copy_from_slice(&[*b, *g, *r, 255]);
+        }
+
+        VideoFrame::bgra(width as u32, height as u32, stride, data).unwrap()
+    }
+
+    fn plane(frame: &VideoFrame, plane: usize) -> Vec<u8> {
+        (0..frame.plane_rows(plane))
+            .flat_map(|y| frame.row(plane, y).to_vec())
+            .collect()
+    }
+
+    fn check_golden(color_space: ColorSpace, expected: [[u8; 3]; 6]) {
+        for (color, [y, u, v]) in COLORS.iter().zip(expected) {
+            let frame = bgra_to_i420(&bgra(2, 2, 0, &[*color; 4]), color_space).unwrap();
+
+            assert_eq!(plane(&frame, 0), vec![y; 4], "Y of {:?}", color);
+            assert_eq!(plane(&frame, 1), vec![u], "U of {:?}", color);
+            assert_eq!(plane(&frame, 2), vec![v], "V of {:?}", color);
+            assert_eq!(frame.color_space, Some(color_space));
+        }
+    }
+
+    #[test]
+    fn bt601_limited_matches_reference() {
+        check_golden(
+            space(ColorMatrix::Bt601, ColorRange::Limited),
+            BT601_LIMITED,
+        );
+    }
+
+    #[test]
+    fn bt601_full_matches_reference() {
+        check_golden(space(ColorMatrix::Bt601, ColorRange::Full), BT601_FULL);
+    }
+
+    #[test]
+    fn bt709_limited_matches_reference() {
+        check_golden(
+            space(ColorMatrix::Bt709, ColorRange::Limited),
+            BT709_LIMITED,
+        );
+    }
+
+    #[test]
+    fn bt709_full_matches_reference() {
+        check_golden(space(ColorMatrix::Bt709, ColorRange::Full), BT709_FULL);
+    }
+
+    #[test]
+    fn odd_sizes_average_only_existing_pixels() {
+        // 3x3, the last column red: the right chroma column only covers it
+        let pixels: Vec<_> = (0..9)
+            .map(|i| if i % 3 == 2 { RED } else { WHITE })
+            .collect();
+        let frame = bgra_to_i420(&bgra(3, 3, 0, &pixels), ColorSpace::default()).unwrap();
+
+        assert_eq!((frame.width, frame.height), (3, 3));
+        assert_eq!(plane(&frame, 0), [235, 235, 81].repeat(3));
+        assert_eq!(plane(&frame, 1), [128, 90].repeat(2));
+        assert_eq!(plane(&frame, 2), [128, 240].repeat(2));
+
+        let frame = bgra_to_i420(&bgra(1, 1, 0, &[RED]), ColorSpace::default()).unwrap();
+        assert_eq!(frame.data, vec![81, 90, 240]);
+
+        // Half red, half white blocks average to the midpoint
+        let frame = bgra_to_i420(&bgra(2, 1, 0, &[RED, WHITE]), ColorSpace::default()).unwrap();
+        assert_eq!(plane(&frame, 0), vec![81, 235]);
+        assert_eq!(plane(&frame, 1), vec![109]);
+        assert_eq!(plane(&frame, 2), vec![184]);
+    }
+
+    #[test]
+    fn padded_stride_matches_tight_rows() {
+        let pixels: Vec<_> = (0..35u8)
+            .map(|i| [i * 7, 255 - i * 5, i.wrapping_mul(37)])
+            .collect();
+
+        for color_space in [
+            space(ColorMatrix::Bt601, ColorRange::Limited),
+            space(ColorMatrix::Bt709, ColorRange::Full),
+        ] {
+            let tight = bgra_to_i420(&bgra(7, 5, 0, &pixels), color_space).unwrap();
+            let padded = bgra_to_i420(&bgra(7, 5, 12, &pixels), color_space).unwrap();
+
+            assert!(padded.is_packed());
+            assert_eq!(padded.data, tight.data);
+        }
+    }
+
+    #[test]
+    fn rejects_i420_input() {
+        let frame = VideoFrame::i420(2, 2, vec![0; 6]).unwrap();
+        assert!(bgra_to_i420(&frame, ColorSpace::default()).is_err());
+    }
+}
@@ -1,4 +1,16 @@
+use crate::share_screen::annotation::AnnotationLayer;
+use crate::share_screen::encoder::VideoCodec;
+use crate::share_screen::encoder::rate::EncoderControl;
+use crate::share_screen::encoder::tuning::EncoderTuning;
+use crate::share_screen::filter::FilterConfig;
+use crate::share_screen::frame::ColorSpace;
+use crate::share_screen::pip::{CameraFeed, PipConfig};
+use crate::share_screen::simulcast::{LayerSink, ThumbnailSlot};
+use crate::share_screen::viewport::ZoomConfig;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaptureSource {
@@ -14,6 +26,15 @@ pub struct CaptureSource {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewOffer {
     pub id: String,
+    pub viewer_id: String,
+    pub sdp: String,
+}
+
+/// Answer to an offer made by the viewer, see `answer_preview_offer`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewAnswer {
+    pub id: String,
+    pub viewer_id: String,
     pub sdp: String,
 }
 
@@ -24,6 +45,25 @@ pub struct CaptureConfig {
     pub height: i32,
     pub withborder: Option<bool>,
     pub withaudio: Option<bool>,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    #[serde(default)]
+    pub codec: VideoCodec,
+    /// Publish full, 720p and 180p layers on the track, for SFU viewers.
+    #[serde(default)]
+    pub simulcast: bool,
+    /// Speed, threading and keyframe controls, or a named preset.
+    #[serde(default)]
+    pub encoder: EncoderTuning,
+    /// Processing applied to captured frames before encoding, in order.
+    #[serde(default)]
+    pub filters: Vec<FilterConfig>,
+    /// Camera picture-in-picture, off when unset.
+    #[serde(default)]
+    pub pip: Option<PipConfig>,
+    /// Zoomed viewport that follows the cursor, off when unset.
+    #[serde(default)]
+    pub zoom: Option<ZoomConfig>,
 }
 
 impl Default for CaptureConfig {
@@ -34,6 +74,13 @@ impl Default for CaptureConfig {
             height: 720,
             withborder: Some(true),
             withaudio: Some(false),
+            color_space: ColorSpace::default(),
+            codec: VideoCodec::default(),
+            simulcast: false,
+            encoder: EncoderTuning::default(),
+            filters: Vec::new(),
+            pip: None,
+            zoom: None,
         }
     }
 }
@@ -74,12 +121,75 @@ pub struct AudioDevice {
     pub is_default: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// Everything a running capture feeds: one queue per encoded layer, the rate
+/// and keyframe control, and the live thumbnail.
+#[derive(Debug, Clone)]
+pub struct CaptureOutputs {
+    pub layers: Vec<LayerSink>,
+    pub control: Arc<EncoderControl>,
+    pub thumbnail: Arc<ThumbnailSlot>,
+    pub annotations: Arc<AnnotationLayer>,
+    /// Set when the stream composites a camera.
+    pub camera: Option<Arc<CameraFeed>>,
+    /// Ends this capture session. Each session of a stream gets its own.
+    pub stop: Arc<AtomicBool>,
+    /// Media time zero of the stream. Shared by all its capture sessions, so
+    /// timestamps keep rising across a source switch.
+    pub epoch: Instant,
+}
+
+/// Payload of `RESOLUTION_CHANGED`, sent when a shared source is resized.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolutionChanged {
+    pub id: String,
+    pub source_width: u32,
+    pub source_height: u32,
+    /// Size of the full quality layer after scaling and filters.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Payload of `ICE_CANDIDATE`, a local candidate of a preview peer. All
+/// fields are unset once gathering is complete.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewIceCandidate {
+    pub id: String,
+    pub viewer_id: String,
+    pub candidate: Option<String>,
+    pub sdp_mid: Option<String>,
+    pub sdp_mline_index: Option<u16>,
+}
+
+/// Payload of `CONNECTION_STATE`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewStateChanged {
+    pub id: String,
+    pub viewer_id: String,
+    #[serde(flatten)]
+    pub change: PreviewState,
+}
+
+/// New state of one layer of a preview peer, as the WebRTC state names.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "state", rename_all = "snake_case")]
+pub enum PreviewState {
+    PeerConnection(String),
+    Ice(String),
+    Dtls(String),
+}
+
 #[derive(Debug)]
 pub struct CaptureStream {
     pub id: String,
     pub source_type: CaptureSourceType,
     pub config: CaptureConfig,
-    pub video_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    pub outputs: CaptureOutputs,
     pub capture_handle: Option<tauri::async_runtime::JoinHandle<()>>,
 }
 
@@ -124,6 +234,12 @@ pub enum CaptureError {
 
     #[error("WebRTC error: {0}")]
     WebRTCError(String),
+
+    #[error("Processing error: {0}")]
+    ProcessingError(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
 }
 
 pub type Result<T> = anyhow::Result<T, CaptureError>;
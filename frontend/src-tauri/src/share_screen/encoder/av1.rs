@@ -0,0 +1,196 @@
+// src/share_screen/encoder/av1.rs
+use super::{EncoderSettings, VideoCodec, VideoEncoder};
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{ColorMatrix, ColorRange, EncodedFrame, PixelFormat, VideoFrame};
+use rav1e::prelude::*;
+use std::time::{Duration, Instant};
+
+// Cuts to half the rate or less are applied without waiting for a keyframe,
+// at most this often
+const URGENT_CUT: u32 = 2;
+const MIN_REBUILD_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct Av1Encoder {
+    ctx: Context<u8>,
+    settings: EncoderSettings,
+    force_keyframe: bool,
+    /// Bitrate to switch to at the next keyframe.
+    pending_kbps: Option<u32>,
+    frames_since_keyframe: u64,
+    last_rebuild: Instant,
+}
+
+impl Av1Encoder {
+    pub fn new(settings: &EncoderSettings) -> Result<Self> {
+        Ok(Self {
+            ctx: Self::new_context(settings)?,
+            settings: settings.clone(),
+            force_keyframe: false,
+            pending_kbps: None,
+            frames_since_keyframe: 0,
+            last_rebuild: Instant::now(),
+        })
+    }
+
+    // A new context starts with a keyframe
+    fn apply_pending_bitrate(&mut self) -> Result<()> {
+        let Some(bitrate_kbps) = self.pending_kbps.take() else {
+            return Ok(());
+        };
+
+        let settings = EncoderSettings {
+            bitrate_kbps,
+            ..self.settings.clone()
+        };
+        self.ctx = Self::new_context(&settings)?;
+        self.settings = settings;
+        self.last_rebuild = Instant::now();
+
+        Ok(())
+    }
+
+    fn new_context(settings: &EncoderSettings) -> Result<Context<u8>> {
+        let (color_primaries, transfer_characteristics, matrix_coefficients) =
+            match settings.color_space.matrix {
+                ColorMatrix::Bt601 => (
+                    ColorPrimaries::BT601,
+                    TransferCharacteristics::BT601,
+                    MatrixCoefficients::BT601,
+                ),
+                ColorMatrix::Bt709 => (
+                    ColorPrimaries::BT709,
+                    TransferCharacteristics::BT709,
+                    MatrixCoefficients::BT709,
+                ),
+            };
+
+        let encoder_config = EncoderConfig {
+            width: settings.width as usize,
+            height: settings.height as usize,
+            time_base: Rational::new(1, settings.fps as u64),
+            bitrate: (settings.bitrate_kbps * 1000) as i32,
+            low_latency: true,
+            max_key_frame_interval: settings.keyframe_interval() as u64,
+            error_resilient: settings.tuning.error_resilient,
+            // PSNR tuning keeps glyph edges, psychovisual smooths them away
+            tune: if settings.tuning.screen_content {
+                Tune::Psnr
+            } else {
+                Tune::Psychovisual
+            },
+            // cpu-used 0..=9 onto rav1e speed 1..=10
+            speed_settings: SpeedSettings::from_preset(
+                settings.tuning.cpu_used.map_or(10, |cpu_used| cpu_used + 1),
+            ),
+            chroma_sampling: ChromaSampling::Cs420,
+            pixel_range: match settings.color_space.range {
+                ColorRange::Limited => PixelRange::Limited,
+                ColorRange::Full => PixelRange::Full,
+            },
+            color_description: Some(ColorDescription {
+                color_primaries,
+                transfer_characteristics,
+                matrix_coefficients,
+            }),
+            ..Default::default()
+        };
+
+        Config::new()
+            .with_encoder_config(encoder_config)
+            .with_threads(settings.threads() as usize)
+            .new_context()
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))
+    }
+}
+
+impl VideoEncoder for Av1Encoder {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::Av1
+    }
+
+    fn encode(&mut self, frame: &VideoFrame) -> Result<Vec<EncodedFrame>> {
+        if frame.format != PixelFormat::I420
+            || frame.width != self.settings.width
+            || frame.height != self.settings.height
+        {
+            return Err(CaptureError::InvalidConfig(format!(
+                "AV1 encoder expects I420 {}x{}, got {:?} {}x{}",
+                self.settings.width, self.settings.height, frame.format, frame.width, frame.height
+            )));
+        }
+
+        // The stream gets a keyframe here anyway, so retargeting is free
+        let keyframe_due = self.force_keyframe
+            || self.frames_since_keyframe + 1 >= self.settings.keyframe_interval().max(1) as u64;
+        if keyframe_due {
+            self.apply_pending_bitrate()?;
+        }
+
+        let mut input = self.ctx.new_frame();
+        for (plane, dst) in input.planes.iter_mut().enumerate() {
+            dst.copy_from_raw_u8(frame.plane(plane), frame.strides[plane], 1);
+        }
+
+        let params = FrameParameters {
+            frame_type_override: if std::mem::take(&mut self.force_keyframe) {
+                FrameTypeOverride::Key
+            } else {
+                FrameTypeOverride::No
+            },
+            ..Default::default()
+        };
+
+        self.ctx
+            .send_frame((input, params))
+            .map_err(|e| CaptureError::ProcessingError(format!("{:?}", e)))?;
+
+        let mut out = Vec::new();
+        loop {
+            match self.ctx.receive_packet() {
+                Ok(packet) => {
+                    let keyframe = packet.frame_type == FrameType::KEY;
+                    self.frames_since_keyframe = if keyframe {
+                        0
+                    } else {
+                        self.frames_since_keyframe + 1
+                    };
+                    out.push(EncodedFrame {
+                        keyframe,
+                        data: packet.data,
+                        timestamp: frame.timestamp,
+                        frame_number: frame.frame_number,
+                    });
+                }
+                Err(EncoderStatus::Encoded) => continue,
+                Err(EncoderStatus::NeedMoreData) | Err(EncoderStatus::LimitReached) => break,
+                Err(e) => return Err(CaptureError::ProcessingError(format!("{:?}", e))),
+            }
+        }
+
+        Ok(out)
+    }
+
+    // rav1e fixes the bitrate at context creation, so a new context (and a
+    // keyframe) is the only way to retarget. Small changes are ignored, and
+    // the rest wait for the next keyframe unless the link needs a steep cut.
+    fn set_bitrate(&mut self, bitrate_kbps: u32) -> Result<()> {
+        let current = self.settings.bitrate_kbps;
+        if bitrate_kbps.abs_diff(current) * 5 < current {
+            self.pending_kbps = None;
+            return Ok(());
+        }
+        self.pending_kbps = Some(bitrate_kbps);
+
+        if bitrate_kbps * URGENT_CUT <= current
+            && self.last_rebuild.elapsed() >= MIN_REBUILD_INTERVAL
+        {
+            self.apply_pending_bitrate()?;
+        }
+
+        Ok(())
+    }
+
+    fn request_keyframe(&mut self) {
+        self.force_keyframe = true;
+    }
+}
@@ -0,0 +1,125 @@
+// src/share_screen/encoder/h264.rs
+use super::{EncoderSettings, VideoCodec, VideoEncoder};
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{EncodedFrame, PixelFormat, VideoFrame};
+use openh264::OpenH264API;
+use openh264::encoder::{
+    Complexity, Encoder, EncoderConfig, FrameType, IntraFramePeriod, UsageType,
+};
+use openh264::formats::YUVSlices;
+use openh264_sys2::{ENCODER_OPTION_BITRATE, SBitrateInfo, SPATIAL_LAYER_ALL};
+use std::os::raw::c_void;
+
+// openh264 does not expose VUI colour fields through its config, so the
+// stream carries no colour description and decoders assume BT.601. It has no
+// error resilience switch or deadline either, those tuning fields are ignored.
+pub struct H264Encoder {
+    encoder: Encoder,
+    width: u32,
+    height: u32,
+}
+
+// The openh264 handle is owned by exactly one encoder thread at a time
+unsafe impl Send for H264Encoder {}
+
+impl H264Encoder {
+    pub fn new(settings: &EncoderSettings) -> Result<Self> {
+        let config = EncoderConfig::new()
+            .set_bitrate_bps(settings.bitrate_kbps * 1000)
+            .max_frame_rate(settings.fps as f32)
+            .enable_skip_frame(false)
+            .num_threads(settings.threads() as u16)
+            .intra_frame_period(IntraFramePeriod::from_num_frames(
+                settings.keyframe_interval(),
+            ))
+            .complexity(match settings.tuning.cpu_used {
+                Some(0..=3) => Complexity::High,
+                Some(4..=6) => Complexity::Medium,
+                _ => Complexity::Low,
+            })
+            .usage_type(if settings.tuning.screen_content {
+                UsageType::ScreenContentRealTime
+            } else {
+                UsageType::CameraVideoRealTime
+            });
+
+        let encoder = Encoder::with_api_config(OpenH264API::from_source(), config)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+
+        Ok(Self {
+            encoder,
+            width: settings.width,
+            height: settings.height,
+        })
+    }
+}
+
+impl VideoEncoder for H264Encoder {
+    fn codec(&self) -> VideoCodec {
+        VideoCodec::H264
+    }
+
+    fn encode(&mut self, frame: &VideoFrame) -> Result<Vec<EncodedFrame>> {
+        if frame.format != PixelFormat::I420
+            || frame.width != self.width
+            || frame.height != self.height
+        {
+            return Err(CaptureError::InvalidConfig(format!(
+                "H.264 encoder expects I420 {}x{}, got {:?} {}x{}",
+                self.width, self.height, frame.format, frame.width, frame.height
+            )));
+        }
+
+        let source = YUVSlices::new(
+            (frame.plane(0), frame.plane(1), frame.plane(2)),
+            (frame.width as usize, frame.height as usize),
+            (frame.strides[0], frame.strides[1], frame.strides[2]),
+        );
+
+        let bitstream = self
+            .encoder
+            .encode(&source)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+
+        let keyframe = matches!(bitstream.frame_type(), FrameType::IDR | FrameType::I);
+        let data = bitstream.to_vec();
+
+        if data.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![EncodedFrame {
+            data,
+            keyframe,
+            timestamp: frame.timestamp,
+            frame_number: frame.frame_number,
+        }])
+    }
+
+    fn set_bitrate(&mut self, bitrate_kbps: u32) -> Result<()> {
+        let mut info = SBitrateInfo {
+            iLayer: SPATIAL_LAYER_ALL,
+            iBitrate: (bitrate_kbps * 1000) as i32,
+        };
+
+        let rc = unsafe {
+            self.encoder.raw_api().set_option(
+                ENCODER_OPTION_BITRATE,
+                &mut info as *mut SBitrateInfo as *mut c_void,
+            )
+        };
+
+        if rc != 0 {
+            return Err(CaptureError::ProcessingError(format!(
+                "openh264 rejected bitrate {} kbps ({})",
+                bitrate_kbps, rc
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn request_keyframe(&mut self) {
+        self.encoder.force_intra_frame();
+    }
+}
@@ -0,0 +1,187 @@
+// src/share_screen/encoder/mod.rs
+#[cfg(windows)]
+pub mod av1;
+#[cfg(windows)]
+pub mod h264;
+pub mod rate;
+pub mod tuning;
+#[cfg(windows)]
+pub mod vpx;
+
+use crate::share_screen::dto::{CaptureConfig, CaptureError, Result};
+use crate::share_screen::encoder::tuning::TuningSettings;
+use crate::share_screen::frame::{ColorSpace, EncodedFrame, VideoFrame};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use webrtc::api::media_engine::{MIME_TYPE_AV1, MIME_TYPE_H264, MIME_TYPE_VP8, MIME_TYPE_VP9};
+use webrtc::rtp_transceiver::RTCPFeedback;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTCRtpCodecParameters};
+
+pub const DEFAULT_BITRATE_KBPS: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    Vp8,
+    Vp9,
+    Av1,
+    H264,
+}
+
+impl VideoCodec {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            VideoCodec::Vp8 => MIME_TYPE_VP8,
+            VideoCodec::Vp9 => MIME_TYPE_VP9,
+            VideoCodec::Av1 => MIME_TYPE_AV1,
+            VideoCodec::H264 => MIME_TYPE_H264,
+        }
+    }
+
+    fn sdp_fmtp_line(self) -> &'static str {
+        match self {
+            VideoCodec::Vp8 => "",
+            VideoCodec::Vp9 => "profile-id=0",
+            VideoCodec::Av1 => "profile-id=0",
+            // Constrained baseline, which is what openh264 produces
+            VideoCodec::H264 => {
+                "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f"
+            }
+        }
+    }
+
+    // Matches the payload types `register_default_codecs` assigns
+    fn payload_type(self) -> u8 {
+        match self {
+            VideoCodec::Vp8 => 96,
+            VideoCodec::Vp9 => 98,
+            VideoCodec::Av1 => 41,
+            VideoCodec::H264 => 125,
+        }
+    }
+
+    pub fn rtp_capability(self) -> RTCRtpCodecCapability {
+        let feedback = |typ: &str, parameter: &str| RTCPFeedback {
+            typ: typ.to_owned(),
+            parameter: parameter.to_owned(),
+        };
+
+        RTCRtpCodecCapability {
+            mime_type: self.mime_type().to_owned(),
+            clock_rate: 90000,
+            channels: 0,
+            sdp_fmtp_line: self.sdp_fmtp_line().to_owned(),
+            rtcp_feedback: vec![
+                feedback("goog-remb", ""),
+                feedback("ccm", "fir"),
+                feedback("nack", ""),
+                feedback("nack", "pli"),
+            ],
+        }
+    }
+
+    /// Parameters used to pin a transceiver to this codec, so the negotiated
+    /// SDP always matches what the encoder produces.
+    pub fn rtp_parameters(self) -> RTCRtpCodecParameters {
+        RTCRtpCodecParameters {
+            capability: self.rtp_capability(),
+            payload_type: self.payload_type(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EncoderSettings {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub bitrate_kbps: u32,
+    pub color_space: ColorSpace,
+    pub tuning: TuningSettings,
+}
+
+impl EncoderSettings {
+    pub fn from_config(config: &CaptureConfig) -> Self {
+        Self {
+            codec: config.codec,
+            width: config.width as u32,
+            height: config.height as u32,
+            fps: config.fps.max(1) as u32,
+            bitrate_kbps: DEFAULT_BITRATE_KBPS,
+            color_space: config.color_space,
+            tuning: config.encoder.resolve(),
+        }
+    }
+
+    /// Rejects settings the codec cannot carry.
+    pub fn check(&self) -> Result<()> {
+        // VP8 has no colour signalling, decoders assume BT.601 limited range
+        if self.codec == VideoCodec::Vp8 && self.color_space != ColorSpace::default() {
+            return Err(CaptureError::InvalidConfig(format!(
+                "VP8 is always BT.601 limited range, got {:?}",
+                self.color_space
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Keyframe interval in frames at the full frame rate, for encoders
+    /// that place keyframes themselves. An idle stream runs far below it, so
+    /// layers also force them by media time, see `keyframe_period`.
+    pub fn keyframe_interval(&self) -> u32 {
+        self.fps * self.tuning.keyframe_interval_secs
+    }
+
+    pub fn keyframe_period(&self) -> Duration {
+        Duration::from_secs(self.tuning.keyframe_interval_secs as u64)
+    }
+
+    /// Thread count with 0 resolved from the core count.
+    pub fn threads(&self) -> u32 {
+        match self.tuning.threads {
+            0 => std::thread::available_parallelism()
+                .map(|n| n.get().min(8) as u32)
+                .unwrap_or(1),
+            threads => threads,
+        }
+    }
+}
+
+pub trait VideoEncoder: Send {
+    fn codec(&self) -> VideoCodec;
+
+    /// Encodes one I420 frame. Encoders may buffer, so a call can return no
+    /// output or several frames.
+    fn encode(&mut self, frame: &VideoFrame) -> Result<Vec<EncodedFrame>>;
+
+    /// Retargets the rate controller without restarting the stream.
+    fn set_bitrate(&mut self, bitrate_kbps: u32) -> Result<()>;
+
+    /// Makes the next encoded frame a keyframe.
+    fn request_keyframe(&mut self);
+}
+
+#[cfg(windows)]
+pub fn create_encoder(settings: &EncoderSettings) -> Result<Box<dyn VideoEncoder>> {
+    settings.check()?;
+
+    Ok(match settings.codec {
+        VideoCodec::Vp8 | VideoCodec::Vp9 => Box::new(vpx::VpxEncoder::new(settings)?),
+        VideoCodec::Av1 => Box::new(av1::Av1Encoder::new(settings)?),
+        VideoCodec::H264 => Box::new(h264::H264Encoder::new(settings)?),
+    })
+}
+
+#[cfg(not(windows))]
+pub fn create_encoder(settings: &EncoderSettings) -> Result<Box<dyn VideoEncoder>> {
+    Err(crate::share_screen::dto::CaptureError::PlatformError(
+        format!(
+            "{:?} encoding is not supported on this platform",
+            settings.codec
+        ),
+    ))
+}
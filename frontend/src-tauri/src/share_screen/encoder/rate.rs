@@ -0,0 +1,146 @@
+// src/share_screen/encoder/rate.rs
+use crate::share_screen::dto::CaptureConfig;
+use crate::share_screen::simulcast::layers_for;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Floor for the adaptive bitrate. Below this text on screen becomes
+/// unreadable, so frame rate is cut instead.
+pub const MIN_BITRATE_KBPS: u32 = 150;
+
+/// State shared between the RTCP reader, which learns about the network and
+/// the viewers, and the encoder thread, which applies it.
+#[derive(Debug)]
+pub struct EncoderControl {
+    target_kbps: AtomicU32,
+    keyframe_requested: AtomicBool,
+    paused: AtomicBool,
+    max_kbps: u32,
+    fps: u32,
+}
+
+impl EncoderControl {
+    pub fn from_config(config: &CaptureConfig) -> Self {
+        // The budget covers every simulcast layer
+        let max_kbps = layers_for(config)
+            .iter()
+            .map(|layer| layer.bitrate_kbps)
+            .sum();
+
+        Self {
+            target_kbps: AtomicU32::new(max_kbps),
+            keyframe_requested: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            max_kbps,
+            fps: config.fps.max(1) as u32,
+        }
+    }
+
+    pub fn max_kbps(&self) -> u32 {
+        self.max_kbps
+    }
+
+    pub fn target_kbps(&self) -> u32 {
+        self.target_kbps.load(Ordering::Relaxed)
+    }
+
+    pub fn set_target_kbps(&self, kbps: u32) {
+        self.target_kbps.store(
+            kbps.clamp(MIN_BITRATE_KBPS, self.max_kbps),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Share of the current target for a layer whose full rate is
+    /// `layer_max_kbps`. Layers scale down together.
+    pub fn layer_kbps(&self, layer_max_kbps: u32) -> u32 {
+        (layer_max_kbps as u64 * self.target_kbps() as u64 / self.max_kbps.max(1) as u64) as u32
+    }
+
+    pub fn request_keyframe(&self) {
+        self.keyframe_requested.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears and returns the pending keyframe request, so bursts of PLIs
+    /// from one loss event cost a single keyframe.
+    pub fn take_keyframe_request(&self) -> bool {
+        self.keyframe_requested.swap(false, Ordering::Relaxed)
+    }
+
+    /// Stops encoding while no viewer is watching. Resuming asks for a
+    /// keyframe, the viewers' decoders lost track in between.
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::Relaxed) && !paused {
+            self.request_keyframe();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Frame rate the encoder should run at for the current target. On a
+    /// starved link fewer, sharper frames beat a smooth but smeared stream.
+    pub fn target_fps(&self) -> u32 {
+        let target = self.target_kbps() as u64;
+        let max = self.max_kbps.max(1) as u64;
+
+        let fps = if target * 4 <= max {
+            self.fps / 3
+        } else if target * 2 <= max {
+            self.fps / 2
+        } else {
+            self.fps
+        };
+
+        fps.max(1)
+    }
+}
+
+/// Send side bandwidth estimate from receiver feedback. Loss reports drive a
+/// simplified GCC loss controller and REMB caps the result.
+pub struct BitrateEstimator {
+    loss_kbps: f64,
+    remb_kbps: Option<f64>,
+    min_kbps: f64,
+    max_kbps: f64,
+}
+
+impl BitrateEstimator {
+    pub fn new(start_kbps: u32, min_kbps: u32, max_kbps: u32) -> Self {
+        Self {
+            loss_kbps: start_kbps as f64,
+            remb_kbps: None,
+            min_kbps: min_kbps as f64,
+            max_kbps: max_kbps as f64,
+        }
+    }
+
+    /// Applies a REMB estimate, in bits per second.
+    pub fn on_remb(&mut self, bitrate_bps: f32) -> u32 {
+        self.remb_kbps = Some(bitrate_bps as f64 / 1000.0);
+        self.target_kbps()
+    }
+
+    /// Applies the `fraction_lost` of a receiver report, in 1/256 units.
+    pub fn on_loss(&mut self, fraction_lost: u8) -> u32 {
+        let loss = fraction_lost as f64 / 256.0;
+
+        if loss > 0.10 {
+            self.loss_kbps *= 1.0 - 0.5 * loss;
+        } else if loss < 0.02 {
+            self.loss_kbps *= 1.08;
+        }
+        self.loss_kbps = self.loss_kbps.clamp(self.min_kbps, self.max_kbps);
+
+        self.target_kbps()
+    }
+
+    pub fn target_kbps(&self) -> u32 {
+        let target = match self.remb_kbps {
+            Some(remb) => self.loss_kbps.min(remb),
+            None => self.loss_kbps,
+        };
+
+        target.clamp(self.min_kbps, self.max_kbps) as u32
+    }
+}
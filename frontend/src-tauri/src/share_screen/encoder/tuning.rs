@@ -0,0 +1,113 @@
+// src/share_screen/encoder/tuning.rs
+use serde::{Deserialize, Serialize};
+
+/// Named starting points for `EncoderTuning`. Fields set explicitly in the
+/// config override the preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncoderPreset {
+    #[default]
+    Balanced,
+    /// Slides, code and documents. Spends more CPU per frame and enables the
+    /// screen content tools so small text stays sharp.
+    CrispText,
+    /// Video and animation. Fastest speed and no screen content tuning.
+    SmoothMotion,
+    /// Poor links. Rare keyframes and error resilient frames.
+    LowBandwidth,
+}
+
+/// libvpx encode deadline. Other encoders are always realtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Deadline {
+    #[default]
+    Realtime,
+    Good,
+}
+
+/// Encoder tuning as sent by the frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncoderTuning {
+    pub preset: EncoderPreset,
+    /// libvpx cpu-used, 0 (slowest) to 9. Mapped onto the rav1e speed and
+    /// the openh264 complexity for the other codecs.
+    pub cpu_used: Option<u8>,
+    pub deadline: Option<Deadline>,
+    /// Encoder threads, 0 picks from the core count.
+    pub threads: Option<u32>,
+    pub keyframe_interval_secs: Option<u32>,
+    pub error_resilient: Option<bool>,
+    pub screen_content: Option<bool>,
+}
+
+/// Tuning with the preset applied, as the encoders consume it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuningSettings {
+    /// `None` keeps the encoder's own realtime default.
+    pub cpu_used: Option<u8>,
+    pub deadline: Deadline,
+    pub threads: u32,
+    pub keyframe_interval_secs: u32,
+    pub error_resilient: bool,
+    pub screen_content: bool,
+}
+
+impl Default for TuningSettings {
+    fn default() -> Self {
+        EncoderPreset::default().settings()
+    }
+}
+
+impl EncoderPreset {
+    pub fn settings(self) -> TuningSettings {
+        let balanced = TuningSettings {
+            cpu_used: None,
+            deadline: Deadline::Realtime,
+            threads: 0,
+            keyframe_interval_secs: 10,
+            error_resilient: true,
+            screen_content: false,
+        };
+
+        match self {
+            EncoderPreset::Balanced => balanced,
+            EncoderPreset::CrispText => TuningSettings {
+                cpu_used: Some(5),
+                error_resilient: false,
+                screen_content: true,
+                ..balanced
+            },
+            EncoderPreset::SmoothMotion => TuningSettings {
+                cpu_used: Some(8),
+                keyframe_interval_secs: 5,
+                ..balanced
+            },
+            EncoderPreset::LowBandwidth => TuningSettings {
+                cpu_used: Some(7),
+                keyframe_interval_secs: 20,
+                screen_content: true,
+                ..balanced
+            },
+        }
+    }
+}
+
+impl EncoderTuning {
+    pub fn resolve(&self) -> TuningSettings {
+        let preset = self.preset.settings();
+
+        TuningSettings {
+            cpu_used: self.cpu_used.map(|speed| speed.min(9)).or(preset.cpu_used),
+            deadline: self.deadline.unwrap_or(preset.deadline),
+            threads: self.threads.unwrap_or(preset.threads),
+            keyframe_interval_secs: self
+                .keyframe_interval_secs
+                .unwrap_or(preset.keyframe_interval_secs)
+                .max(1),
+            error_resilient: self.error_resilient.unwrap_or(preset.error_resilient),
+            screen_content: self.screen_content.unwrap_or(preset.screen_content),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+// src/share_screen/encoder/vpx.rs
+use super::tuning::Deadline;
+use super::{EncoderSettings, VideoCodec, VideoEncoder};
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{ColorMatrix, ColorRange, EncodedFrame, PixelFormat, VideoFrame};
+use std::mem::MaybeUninit;
+use std::os::raw::{c_int, c_ulong};
+use std::ptr;
+use vpx_sys::*;
+
+/// VP8/VP9 encoder on raw libvpx. `vpx-encode` hides the frame flags and the
+/// control interface, which rate and keyframe control need.
+pub struct VpxEncoder {
+    codec: VideoCodec,
+    // Boxed so the addresses libvpx keeps stay valid when the encoder moves
+    ctx: Box<vpx_codec_ctx_t>,
+    cfg: Box<vpx_codec_enc_cfg_t>,
+    frame_duration_ms: c_ulong,
+    deadline: c_ulong,
+    force_keyframe: bool,
+}
+
+// The libvpx context is owned by exactly one encoder thread at a time
+unsafe impl Send for VpxEncoder {}
+
+fn check(err: vpx_codec_err_t, what: &str) -> Result<()> {
+    if err == vpx_codec_err_t::VPX_CODEC_OK {
+        Ok(())
+    } else {
+        Err(CaptureError::ProcessingError(format!(
+            "{} failed: {:?}",
+            what, err
+        )))
+    }
+}
+
+impl VpxEncoder {
+    pub fn new(settings: &EncoderSettings) -> Result<Self> {
+        unsafe {
+            let iface = match settings.codec {
+                VideoCodec::Vp8 => vpx_codec_vp8_cx(),
+                VideoCodec::Vp9 => vpx_codec_vp9_cx(),
+                other => {
+                    return Err(CaptureError::InvalidConfig(format!(
+                        "{:?} is not a libvpx codec",
+                        other
+                    )));
+                }
+            };
+
+            let mut cfg = Box::new(MaybeUninit::<vpx_codec_enc_cfg_t>::uninit());
+            check(
+                vpx_codec_enc_config_default(iface, cfg.as_mut_ptr(), 0),
+                "vpx_codec_enc_config_default",
+            )?;
+            let mut cfg = cfg.assume_init();
+
+            cfg.g_w = settings.width;
+            cfg.g_h = settings.height;
+            // Millisecond timebase, pts comes from the capture timestamp
+            cfg.g_timebase.num = 1;
+            cfg.g_timebase.den = 1000;
+            cfg.g_threads = settings.threads();
+            cfg.g_lag_in_frames = 0;
+            cfg.g_error_resilient = if settings.tuning.error_resilient {
+                VPX_ERROR_RESILIENT_DEFAULT
+            } else {
+                0
+            };
+            cfg.rc_end_usage = vpx_rc_mode::VPX_CBR;
+            cfg.rc_target_bitrate = settings.bitrate_kbps;
+            cfg.kf_mode = vpx_kf_mode::VPX_KF_AUTO;
+            cfg.kf_max_dist = settings.keyframe_interval();
+
+            let mut ctx: Box<vpx_codec_ctx_t> = Box::new(MaybeUninit::zeroed().assume_init());
+            check(
+                vpx_codec_enc_init_ver(
+                    &mut *ctx,
+                    iface,
+                    &*cfg,
+                    0,
+                    VPX_ENCODER_ABI_VERSION as c_int,
+                ),
+                "vpx_codec_enc_init",
+            )?;
+
+            let mut encoder = Self {
+                codec: settings.codec,
+                ctx,
+                cfg,
+                frame_duration_ms: (1000 / settings.fps.max(1)) as c_ulong,
+                deadline: match settings.tuning.deadline {
+                    Deadline::Realtime => VPX_DL_REALTIME as c_ulong,
+                    Deadline::Good => VPX_DL_GOOD_QUALITY as c_ulong,
+                },
+                force_keyframe: false,
+            };
+
+            // Realtime speed, higher is faster and blurrier
+            let cpu_used = match settings.tuning.cpu_used {
+                Some(cpu_used) => cpu_used as c_int,
+                None if settings.codec == VideoCodec::Vp9 => 7,
+                None => 6,
+            };
+            encoder.control(vp8e_enc_control_id::VP8E_SET_CPUUSED as c_int, cpu_used)?;
+
+            if settings.tuning.screen_content {
+                match settings.codec {
+                    VideoCodec::Vp9 => encoder.control(
+                        vp8e_enc_control_id::VP9E_SET_TUNE_CONTENT as c_int,
+                        vp9e_tune_content::VP9E_CONTENT_SCREEN as c_int,
+                    )?,
+                    _ => encoder.control(
+                        vp8e_enc_control_id::VP8E_SET_SCREEN_CONTENT_MODE as c_int,
+                        1,
+                    )?,
+                }
+            }
+
+            // VP8 has no colour signalling, `check` keeps it on the default
+            if settings.codec == VideoCodec::Vp9 {
+                let color_space = match settings.color_space.matrix {
+                    ColorMatrix::Bt601 => vpx_color_space::VPX_CS_BT_601,
+                    ColorMatrix::Bt709 => vpx_color_space::VPX_CS_BT_709,
+                };
+                let color_range = match settings.color_space.range {
+                    ColorRange::Limited => vpx_color_range::VPX_CR_STUDIO_RANGE,
+                    ColorRange::Full => vpx_color_range::VPX_CR_FULL_RANGE,
+                };
+                encoder.control(
+                    vp8e_enc_control_id::VP9E_SET_COLOR_SPACE as c_int,
+                    color_space as c_int,
+                )?;
+                encoder.control(
+                    vp8e_enc_control_id::VP9E_SET_COLOR_RANGE as c_int,
+                    color_range as c_int,
+                )?;
+            }
+
+            Ok(encoder)
+        }
+    }
+
+    fn control(&mut self, id: c_int, value: c_int) -> Result<()> {
+        unsafe {
+            check(
+                vpx_codec_control_(&mut *self.ctx, id, value),
+                "vpx_codec_control",
+            )
+        }
+    }
+}
+
+impl VideoEncoder for VpxEncoder {
+    fn codec(&self) -> VideoCodec {
+        self.codec
+    }
+
+    fn encode(&mut self, frame: &VideoFrame) -> Result<Vec<EncodedFrame>> {
+        if frame.format != PixelFormat::I420 {
+            return Err(CaptureError::ProcessingError(format!(
+                "libvpx expects I420, got {:?}",
+                frame.format
+            )));
+        }
+
+        if frame.width != self.cfg.g_w || frame.height != self.cfg.g_h {
+            return Err(CaptureError::InvalidConfig(format!(
+                "Frame is {}x{} but the encoder was configured for {}x{}",
+                frame.width, frame.height, self.cfg.g_w, self.cfg.g_h
+            )));
+        }
+
+        unsafe {
+            let mut image = MaybeUninit::<vpx_image_t>::zeroed();
+            let img = vpx_img_wrap(
+                image.as_mut_ptr(),
+                vpx_img_fmt::VPX_IMG_FMT_I420,
+                frame.width,
+                frame.height,
+                1,
+                frame.data.as_ptr() as *mut u8,
+            );
+            if img.is_null() {
+                return Err(CaptureError::ProcessingError(
+                    "vpx_img_wrap failed".to_string(),
+                ));
+            }
+
+            // Point libvpx at each plane with its real stride
+            for (plane, stride) in frame.strides.iter().enumerate() {
+                (*img).planes[plane] = frame.plane(plane).as_ptr() as *mut u8;
+                (*img).stride[plane] = *stride as c_int;
+            }
+
+            let flags = if std::mem::take(&mut self.force_keyframe) {
+                VPX_EFLAG_FORCE_KF as vpx_enc_frame_flags_t
+            } else {
+                0
+            };
+
+            check(
+                vpx_codec_encode(
+                    &mut *self.ctx,
+                    img,
+                    frame.timestamp.as_millis() as vpx_codec_pts_t,
+                    self.frame_duration_ms,
+                    flags,
+                    self.deadline,
+                ),
+                "vpx_codec_encode",
+            )?;
+
+            let mut out = Vec::new();
+            let mut iter: vpx_codec_iter_t = ptr::null();
+
+            loop {
+                let pkt = vpx_codec_get_cx_data(&mut *self.ctx, &mut iter);
+                if pkt.is_null() {
+                    break;
+                }
+
+                if (*pkt).kind != vpx_codec_cx_pkt_kind::VPX_CODEC_CX_FRAME_PKT {
+                    continue;
+                }
+
+                let data = &(*pkt).data.frame;
+                out.push(EncodedFrame {
+                    data: std::slice::from_raw_parts(data.buf as *const u8, data.sz as usize)
+                        .to_vec(),
+                    keyframe: data.flags & VPX_FRAME_IS_KEY != 0,
+                    timestamp: frame.timestamp,
+                    frame_number: frame.frame_number,
+                });
+            }
+
+            Ok(out)
+        }
+    }
+
+    fn set_bitrate(&mut self, bitrate_kbps: u32) -> Result<()> {
+        self.cfg.rc_target_bitrate = bitrate_kbps;
+        unsafe {
+            check(
+                vpx_codec_enc_config_set(&mut *self.ctx, &*self.cfg),
+                "vpx_codec_enc_config_set",
+            )
+        }
+    }
+
+    fn request_keyframe(&mut self) {
+        self.force_keyframe = true;
+    }
+}
+
+impl Drop for VpxEncoder {
+    fn drop(&mut self) {
+        unsafe {
+            vpx_codec_destroy(&mut *self.ctx);
+        }
+    }
+}
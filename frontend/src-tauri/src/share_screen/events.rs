@@ -0,0 +1,30 @@
+// src/share_screen/events.rs
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter};
+
+pub const RESOLUTION_CHANGED: &str = "share-screen-resolution-changed";
+pub const ICE_CANDIDATE: &str = "share-screen-ice-candidate";
+pub const CONNECTION_STATE: &str = "share-screen-connection-state";
+pub const STREAM_STATS: &str = "share-screen-stats";
+pub const CONTROL_MESSAGE: &str = "share-screen-control-message";
+pub const REMOTE_CONTROL: &str = "share-screen-remote-control";
+
+lazy_static! {
+    static ref APP_HANDLE: RwLock<Option<AppHandle>> = RwLock::new(None);
+}
+
+/// Called once from the app setup, before any capture starts.
+pub fn init(handle: AppHandle) {
+    *APP_HANDLE.write().unwrap() = Some(handle);
+}
+
+/// Sends `payload` to the frontend. Events raised before `init` are dropped.
+pub fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(handle) = APP_HANDLE.read().unwrap().as_ref()
+        && let Err(e) = handle.emit(event, payload)
+    {
+        eprintln!("[Events] Failed to emit {}: {:?}", event, e);
+    }
+}
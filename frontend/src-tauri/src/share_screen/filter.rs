@@ -0,0 +1,344 @@
+// src/share_screen/filter.rs
+use crate::share_screen::convert::bgra_to_i420;
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{ColorSpace, PixelFormat, VideoFrame};
+use crate::share_screen::scale::{FrameScaler, ScaleFilter};
+use serde::{Deserialize, Serialize};
+
+/// One processing step between capture and encode. Filters run on the
+/// encoder thread in the order they were added, each getting the previous
+/// filter's output.
+pub trait FrameFilter: Send {
+    fn name(&self) -> &'static str;
+
+    /// Size of the frames this filter produces from `width` x `height` input.
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (width, height)
+    }
+
+    /// Changes whenever the filter's own input changes, such as an overlay
+    /// being edited. Lets the encoder redraw a static screen.
+    fn revision(&self) -> u64 {
+        0
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame>;
+}
+
+/// Filters that can be set up from the frontend through `CaptureConfig`.
+/// Overlays that need live state are pushed onto the chain in code instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterConfig {
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    Scale {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        filter: ScaleFilter,
+    },
+    /// Centers the frame on a larger canvas of `color` (BGRA).
+    Pad {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        color: [u8; 4],
+    },
+    /// Converts to I420 in the stream's colour space, so the layers skip the
+    /// BGRA path. Only allowed as the last filter, the others and the
+    /// overlays need BGRA.
+    ColorConvert,
+}
+
+impl FilterConfig {
+    /// `color_space` is the one the encoder signals, which a conversion has
+    /// to match.
+    pub fn build(&self, color_space: ColorSpace) -> Box<dyn FrameFilter> {
+        match *self {
+            FilterConfig::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => Box::new(CropFilter {
+                x,
+                y,
+                width,
+                height,
+            }),
+            FilterConfig::Scale {
+                width,
+                height,
+                filter,
+            } => Box::new(ScaleStage {
+                width,
+                height,
+                scaler: FrameScaler::new(filter),
+            }),
+            FilterConfig::Pad {
+                width,
+                height,
+                color,
+            } => Box::new(PadFilter {
+                width,
+                height,
+                color,
+            }),
+            FilterConfig::ColorConvert => Box::new(ColorConvertFilter { color_space }),
+        }
+    }
+}
+
+/// Ordered list of filters for one stream. A configured colour conversion
+/// is kept apart and always runs last.
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn FrameFilter>>,
+    convert: Option<Box<dyn FrameFilter>>,
+}
+
+impl FilterChain {
+    /// Rejects filter lists that would hand I420 frames to a BGRA filter,
+    /// or fail on every frame.
+    pub fn check_config(configs: &[FilterConfig]) -> Result<()> {
+        let convert = configs
+            .iter()
+            .position(|config| matches!(config, FilterConfig::ColorConvert));
+        if convert.is_some_and(|at| at + 1 != configs.len()) {
+            return Err(CaptureError::InvalidConfig(
+                "color_convert has to be the last filter".to_string(),
+            ));
+        }
+
+        if configs.iter().any(|config| {
+            matches!(config, FilterConfig::Scale { width, height, .. } if *width == 0 || *height == 0)
+        }) {
+            return Err(CaptureError::InvalidConfig(
+                "scale needs a non-zero width and height".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn from_config(configs: &[FilterConfig], color_space: ColorSpace) -> Self {
+        let (convert, configs) = match configs.split_last() {
+            Some((last @ FilterConfig::ColorConvert, rest)) => {
+                (Some(last.build(color_space)), rest)
+            }
+            _ => (None, configs),
+        };
+
+        Self {
+            filters: configs
+                .iter()
+                .map(|config| config.build(color_space))
+                .collect(),
+            convert,
+        }
+    }
+
+    /// Adds a filter after the configured ones, still ahead of the colour
+    /// conversion.
+    pub fn push(&mut self, filter: Box<dyn FrameFilter>) {
+        self.filters.push(filter);
+    }
+
+    /// Adds a filter that must see frames before the configured ones.
+    pub fn prepend(&mut self, filter: Box<dyn FrameFilter>) {
+        self.filters.insert(0, filter);
+    }
+
+    /// True when frames leave the chain as BGRA.
+    pub fn outputs_bgra(&self) -> bool {
+        self.convert.is_none()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.convert.is_none()
+    }
+
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        self.filters
+            .iter()
+            .chain(&self.convert)
+            .fold((width, height), |(w, h), filter| filter.output_size(w, h))
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.filters
+            .iter()
+            .chain(&self.convert)
+            .fold(0, |sum, filter| sum.wrapping_add(filter.revision()))
+    }
+
+    pub fn apply(&mut self, mut frame: VideoFrame) -> Result<VideoFrame> {
+        for filter in self.filters.iter_mut().chain(&mut self.convert) {
+            frame = filter.apply(frame).map_err(|e| {
+                CaptureError::ProcessingError(format!("{} filter: {}", filter.name(), e))
+            })?;
+        }
+
+        Ok(frame)
+    }
+}
+
+struct CropFilter {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl FrameFilter for CropFilter {
+    fn name(&self) -> &'static str {
+        "crop"
+    }
+
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (_, _, w, h) = crop_rect(width, height, self.x, self.y, self.width, self.height);
+        (w, h)
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        crop(&frame, self.x, self.y, self.width, self.height)
+    }
+}
+
+// Clamped to the frame, and to even offsets so I420 chroma stays aligned
+fn crop_rect(
+    frame_width: u32,
+    frame_height: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> (u32, u32, u32, u32) {
+    let x = (x & !1).min(frame_width.saturating_sub(2));
+    let y = (y & !1).min(frame_height.saturating_sub(2));
+    let w = width.min(frame_width - x).max(1);
+    let h = height.min(frame_height - y).max(1);
+    (x, y, w, h)
+}
+
+/// Copies the part of `frame` inside the given rect, clamped to the frame.
+pub fn crop(frame: &VideoFrame, x: u32, y: u32, width: u32, height: u32) -> Result<VideoFrame> {
+    let (x, y, width, height) = crop_rect(frame.width, frame.height, x, y, width, height);
+    if (x, y, width, height) == (0, 0, frame.width, frame.height) {
+        return Ok(frame.clone());
+    }
+
+    let format = frame.format;
+    let mut data = Vec::new();
+    let mut strides = [0; 3];
+
+    for (plane, stride) in strides.iter_mut().enumerate().take(format.plane_count()) {
+        let (px, py) = format.plane_size(plane, x, y);
+        let (pw, ph) = format.plane_size(plane, width, height);
+        let bytes = format.bytes_per_sample();
+        let start = px as usize * bytes;
+
+        for row in py..py + ph {
+            data.extend_from_slice(
+                &frame.row(plane, row as usize)[start..start + pw as usize * bytes],
+            );
+        }
+        *stride = pw as usize * bytes;
+    }
+
+    Ok(VideoFrame {
+        color_space: frame.color_space,
+        ..VideoFrame::new(format, width, height, strides, data)?
+            .with_timing(frame.timestamp, frame.frame_number)
+    })
+}
+
+struct ScaleStage {
+    width: u32,
+    height: u32,
+    scaler: FrameScaler,
+}
+
+impl FrameFilter for ScaleStage {
+    fn name(&self) -> &'static str {
+        "scale"
+    }
+
+    fn output_size(&self, _width: u32, _height: u32) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        if (frame.width, frame.height) == (self.width, self.height) {
+            return Ok(frame);
+        }
+
+        self.scaler.scale_frame(&frame, self.width, self.height)
+    }
+}
+
+struct PadFilter {
+    width: u32,
+    height: u32,
+    color: [u8; 4],
+}
+
+impl FrameFilter for PadFilter {
+    fn name(&self) -> &'static str {
+        "pad"
+    }
+
+    fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (self.width.max(width), self.height.max(height))
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        if frame.format != PixelFormat::Bgra {
+            return Err(CaptureError::ProcessingError(format!(
+                "Expected a BGRA frame, got {:?}",
+                frame.format
+            )));
+        }
+
+        let (width, height) = self.output_size(frame.width, frame.height);
+        if (width, height) == (frame.width, frame.height) {
+            return Ok(frame);
+        }
+
+        let stride = width as usize * 4;
+        let mut data = self.color.repeat(width as usize * height as usize);
+        let left = (width - frame.width) as usize / 2 * 4;
+        let top = (height - frame.height) as usize / 2;
+
+        for y in 0..frame.height as usize {
+            let start = (top + y) * stride + left;
+            let row = frame.row(0, y);
+            data[start..start + row.len()].copy_from_slice(row);
+        }
+
+        Ok(VideoFrame::bgra(width, height, stride, data)?
+            .with_timing(frame.timestamp, frame.frame_number))
+    }
+}
+
+struct ColorConvertFilter {
+    color_space: ColorSpace,
+}
+
+impl FrameFilter for ColorConvertFilter {
+    fn name(&self) -> &'static str {
+        "color_convert"
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        match frame.format {
+            PixelFormat::Bgra => bgra_to_i420(&frame, self.color_space),
+            PixelFormat::I420 => Ok(frame),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// src/share_screen/frame.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Bgra,
+    I420,
+}
+
+impl PixelFormat {
+    pub fn plane_count(self) -> usize {
+        match self {
+            PixelFormat::Bgra => 1,
+            PixelFormat::I420 => 3,
+        }
+    }
+
+    /// Width and height in samples of `plane` for a `width` x `height` image.
+    pub fn plane_size(self, plane: usize, width: u32, height: u32) -> (u32, u32) {
+        match (self, plane) {
+            (PixelFormat::I420, 1 | 2) => (width.div_ceil(2), height.div_ceil(2)),
+            _ => (width, height),
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            PixelFormat::Bgra => 4,
+            PixelFormat::I420 => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMatrix {
+    Bt601,
+    Bt709,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorRange {
+    Limited,
+    Full,
+}
+
+/// YUV matrix and quantisation range of a frame. VP8 can only signal
+/// BT.601 limited range, which is why that is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorSpace {
+    pub matrix: ColorMatrix,
+    pub range: ColorRange,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        Self {
+            matrix: ColorMatrix::Bt601,
+            range: ColorRange::Limited,
+        }
+    }
+}
+
+/// A raw frame as it moves from capture through conversion into the encoder.
+///
+/// Planes are stored back to back in `data`. Each row of plane `n` starts
+/// `strides[n]` bytes after the previous one, which may be wider than the
+/// visible row (D3D11 `RowPitch` padding, for example).
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub strides: [usize; 3],
+    pub data: Vec<u8>,
+    /// Monotonic capture time, WGC's `SystemRelativeTime` on Windows.
+    pub timestamp: Duration,
+    pub frame_number: u64,
+    /// Set for YUV formats so the encoder can signal it, `None` for RGB.
+    pub color_space: Option<ColorSpace>,
+}
+
+impl VideoFrame {
+    pub fn new(
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+        strides: [usize; 3],
+        data: Vec<u8>,
+    ) -> Result<Self> {
+        let frame = Self {
+            format,
+            width,
+            height,
+            strides,
+            data,
+            timestamp: Duration::ZERO,
+            frame_number: 0,
+            color_space: None,
+        };
+
+        let needed = frame.plane_offset(format.plane_count());
+        if frame.data.len() < needed {
+            return Err(CaptureError::ProcessingError(format!(
+                "{:?} frame {}x{} needs {} bytes, got {}",
+                format,
+                width,
+                height,
+                needed,
+                frame.data.len()
+            )));
+        }
+
+        for plane in 0..format.plane_count() {
+            if frame.strides[plane] < frame.row_bytes(plane) {
+                return Err(CaptureError::ProcessingError(format!(
+                    "Stride {} of plane {} is narrower than its {} byte rows",
+                    frame.strides[plane],
+                    plane,
+                    frame.row_bytes(plane)
+                )));
+            }
+        }
+
+        Ok(frame)
+    }
+
+    pub fn bgra(width: u32, height: u32, stride: usize, data: Vec<u8>) -> Result<Self> {
+        Self::new(PixelFormat::Bgra, width, height, [stride, 0, 0], data)
+    }
+
+    /// Tightly packed I420, chroma planes rounded up for odd sizes.
+    pub fn i420(width: u32, height: u32, data: Vec<u8>) -> Result<Self> {
+        let chroma = width.div_ceil(2) as usize;
+        Self::new(
+            PixelFormat::I420,
+            width,
+            height,
+            [width as usize, chroma, chroma],
+            data,
+        )
+    }
+
+    pub fn with_timing(mut self, timestamp: Duration, frame_number: u64) -> Self {
+        self.timestamp = timestamp;
+        self.frame_number = frame_number;
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    /// Visible bytes in one row of `plane`, excluding padding.
+    pub fn row_bytes(&self, plane: usize) -> usize {
+        let (w, _) = self.format.plane_size(plane, self.width, self.height);
+        w as usize * self.format.bytes_per_sample()
+    }
+
+    pub fn plane_rows(&self, plane: usize) -> usize {
+        self.format.plane_size(plane, self.width, self.height).1 as usize
+    }
+
+    fn plane_offset(&self, plane: usize) -> usize {
+        (0..plane)
+            .map(|p| self.strides[p] * self.plane_rows(p))
+            .sum()
+    }
+
+    pub fn plane(&self, plane: usize) -> &[u8] {
+        let start = self.plane_offset(plane);
+        &self.data[start..start + self.strides[plane] * self.plane_rows(plane)]
+    }
+
+    pub fn plane_mut(&mut self, plane: usize) -> &mut [u8] {
+        let start = self.plane_offset(plane);
+        let len = self.strides[plane] * self.plane_rows(plane);
+        &mut self.data[start..start + len]
+    }
+
+    pub fn row(&self, plane: usize, y: usize) -> &[u8] {
+        let stride = self.strides[plane];
+        &self.plane(plane)[y * stride..y * stride + self.row_bytes(plane)]
+    }
+
+    pub fn is_packed(&self) -> bool {
+        (0..self.format.plane_count()).all(|p| self.strides[p] == self.row_bytes(p))
+    }
+
+    /// Drops row padding so `data` can be handed to APIs that assume tightly
+    /// packed planes, such as libvpx's `vpx_img_wrap`.
+    pub fn into_packed(self) -> Self {
+        if self.is_packed() {
+            return self;
+        }
+
+        let mut data = Vec::with_capacity(
+            (0..self.format.plane_count())
+                .map(|p| self.row_bytes(p) * self.plane_rows(p))
+                .sum(),
+        );
+        let mut strides = [0; 3];
+
+        for (plane, stride) in strides
+            .iter_mut()
+            .enumerate()
+            .take(self.format.plane_count())
+        {
+            for y in 0..self.plane_rows(plane) {
+                data.extend_from_slice(self.row(plane, y));
+            }
+            *stride = self.row_bytes(plane);
+        }
+
+        Self {
+            strides,
+            data,
+            ..self
+        }
+    }
+}
+
+/// Compressed output of the encoder, carrying the timing of the frame it
+/// was produced from.
+#[derive(Debug, Clone)]
+pub struct EncodedFrame {
+    pub data: Vec<u8>,
+    pub keyframe: bool,
+    pub timestamp: Duration,
+    pub frame_number: u64,
+}
@@ -1,12 +1,23 @@
 // src/share_screen/manager.rs
 use crate::share_screen::{
+    annotation::AnnotationLayer,
     capture::CaptureDevice,
     dto::{
-        AudioDevice, CaptureConfig, CaptureError, CaptureSource, CaptureSourceType, CaptureStream,
-        Result,
+        AudioDevice, CameraInfo, CaptureConfig, CaptureError, CaptureOutputs, CaptureSource,
+        CaptureSourceType, CaptureStream, Result,
     },
+    encoder::{EncoderSettings, rate::EncoderControl},
+    filter::FilterChain,
+    pip::CameraFeed,
+    queue::{ENCODED_QUEUE, frame_queue},
+    remote::{self, RevokeReason},
+    simulcast::{LayerSink, ThumbnailSlot, layers_for, thumbnail_png},
+    stats::{self, LayerStats, StreamStats},
 };
 use base64::{Engine as _, engine::general_purpose};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 
 pub struct CaptureManager {
     active_streams: DashMap<String, CaptureStream>,
@@ -39,7 +50,7 @@ impl CaptureManager {
             //     .capture_single_frame_monitor(monitor.hmonitor, config.width, config.height)
             //     .await?;
 
-            let thumbnail = "".to_string();
+            let thumbnail = self.live_thumbnail(&CaptureSourceType::Monitor(monitor.hmonitor));
 
             sources.push(CaptureSource {
                 id: CaptureSourceType::Monitor(monitor.hmonitor).to_id(),
@@ -65,7 +76,7 @@ impl CaptureManager {
             //     .capture_single_frame_window(window.hwnd, config.width, config.height)
             //     .await?;
 
-            let thumbnail = "".to_string();
+            let thumbnail = self.live_thumbnail(&CaptureSourceType::Window(window.hwnd));
 
             let icon = window
                 .icon
@@ -96,7 +107,7 @@ impl CaptureManager {
         //     .capture_single_frame_monitor(hmonitor, config.width, config.height)
         //     .await?;
 
-        let thumbnail = "".to_string();
+        let thumbnail = self.live_thumbnail(&CaptureSourceType::Monitor(hmonitor));
 
         Ok(CaptureSource {
             id: CaptureSourceType::Monitor(hmonitor).to_id(),
@@ -120,7 +131,7 @@ impl CaptureManager {
         //     .capture_single_frame_window(hwnd, config.width, config.height)
         //     .await?;
 
-        let thumbnail = "".to_string();
+        let thumbnail = self.live_thumbnail(&CaptureSourceType::Window(hwnd));
 
         let icon = window
             .icon
@@ -144,48 +155,64 @@ impl CaptureManager {
     ) -> Result<()> {
         let id = source_type.to_id();
 
-        if self.active_streams.contains_key(&id) {
+        if self.active_streams.contains_key(&id) || self.stream_id_for(&source_type).is_some() {
             return Err(CaptureError::CaptureAlreadyActive(id));
         }
+        FilterChain::check_config(&config.filters)?;
+        EncoderSettings::from_config(&config).check()?;
+
+        let control = Arc::new(EncoderControl::from_config(&config));
+
+        // One encoded queue per simulcast layer
+        let mut sinks = Vec::new();
+        let mut receivers = Vec::new();
+        for layer in layers_for(&config) {
+            let (video_tx, video_rx) = frame_queue(ENCODED_QUEUE);
+            sinks.push(LayerSink {
+                layer,
+                video_tx,
+                counters: Arc::default(),
+            });
+            receivers.push((layer, video_rx));
+        }
 
-        let (video_tx, video_rx) = tokio::sync::mpsc::channel(100);
+        let outputs = CaptureOutputs {
+            layers: sinks,
+            control: control.clone(),
+            thumbnail: Arc::new(ThumbnailSlot::default()),
+            annotations: Arc::new(AnnotationLayer::default()),
+            camera: config.pip.as_ref().map(|_| Arc::new(CameraFeed::default())),
+            stop: Arc::default(),
+            epoch: Instant::now(),
+        };
+
+        // The camera is the only part that can fail to open, so it goes
+        // before anything is registered
+        if let (Some(pip), Some(feed)) = (&config.pip, &outputs.camera) {
+            let capture_device = self.capture_device.clone();
+            let (device_id, feed) = (pip.device_id.clone(), feed.clone());
+
+            // Opening blocks until the device is up
+            async_runtime::spawn_blocking(move || capture_device.start_camera(device_id, feed))
+                .await
+                .map_err(|e| CaptureError::PlatformError(e.to_string()))??;
+        }
 
-        let video_tx_for_task = video_tx.clone();
         // Register with WebRTC server
-        crate::share_screen::WEBRTC_SERVER
+        let registered = crate::share_screen::WEBRTC_SERVER
             .write()
             .await
-            .create_preview_track(&id, video_rx)
-            .await
-            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
-
-        // Start capture
-        let capture_device = self.capture_device.clone();
-        let source_type_clone = source_type.clone();
-        let config_clone = config.clone();
+            .create_preview_track(&id, config.codec, control, receivers)
+            .await;
+        if let Err(e) = registered {
+            if let Some(camera) = &outputs.camera {
+                camera.stop();
+            }
+            return Err(CaptureError::WebRTCError(e.to_string()));
+        }
 
-        // Spawn blocking task to preserve !Send handle
-        let handle: tauri::async_runtime::JoinHandle<()> =
-            async_runtime::spawn_blocking(move || {
-                futures::executor::block_on(async move {
-                    let result = match source_type_clone {
-                        CaptureSourceType::Monitor(hmonitor) => {
-                            capture_device
-                                .start_monitor_capture(hmonitor, config_clone, video_tx_for_task)
-                                .await
-                        }
-                        CaptureSourceType::Window(hwnd) => {
-                            capture_device
-                                .start_window_capture(hwnd, config_clone, video_tx_for_task)
-                                .await
-                        }
-                    };
-
-                    if let Err(e) = result {
-                        eprintln!("Capture error: {:?}", e);
-                    }
-                })
-            });
+        let handle = self.spawn_capture(source_type.clone(), config.clone(), outputs.clone());
+        stats::spawn_reporter(id.clone());
 
         self.active_streams.insert(
             id.clone(),
@@ -193,7 +220,7 @@ impl CaptureManager {
                 id,
                 source_type,
                 config,
-                video_tx,
+                outputs,
                 capture_handle: Some(handle),
             },
         );
@@ -201,12 +228,151 @@ impl CaptureManager {
         Ok(())
     }
 
+    fn spawn_capture(
+        &self,
+        source_type: CaptureSourceType,
+        config: CaptureConfig,
+        outputs: CaptureOutputs,
+    ) -> tauri::async_runtime::JoinHandle<()> {
+        let capture_device = self.capture_device.clone();
+
+        // Spawn blocking task to preserve !Send handle
+        async_runtime::spawn_blocking(move || {
+            futures::executor::block_on(async move {
+                let result = match source_type {
+                    CaptureSourceType::Monitor(hmonitor) => {
+                        capture_device
+                            .start_monitor_capture(hmonitor, config, outputs)
+                            .await
+                    }
+                    CaptureSourceType::Window(hwnd) => {
+                        capture_device
+                            .start_window_capture(hwnd, config, outputs)
+                            .await
+                    }
+                };
+
+                if let Err(e) = result {
+                    eprintln!("Capture error: {:?}", e);
+                }
+            })
+        })
+    }
+
+    /// Stops the current capture of a preview that is moving to
+    /// `source_type`, and hands back its task. The caller waits for it
+    /// without holding the manager, then calls `switch_source`. `None` when
+    /// the preview already shows that source.
+    pub fn begin_switch(
+        &mut self,
+        id: &str,
+        source_type: &CaptureSourceType,
+    ) -> Result<Option<tauri::async_runtime::JoinHandle<()>>> {
+        if let Some(other) = self.stream_id_for(source_type)
+            && other != id
+        {
+            return Err(CaptureError::CaptureAlreadyActive(other));
+        }
+
+        let mut stream = self
+            .active_streams
+            .get_mut(id)
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
+        if stream.source_type == *source_type {
+            return Ok(None);
+        }
+        // Taken by a switch that has not finished yet
+        let handle = stream
+            .capture_handle
+            .take()
+            .ok_or_else(|| CaptureError::CaptureAlreadyActive(id.to_string()))?;
+
+        stream.outputs.stop.store(true, Ordering::Relaxed);
+        Ok(Some(handle))
+    }
+
+    /// Moves a running preview to another source, once the capture stopped
+    /// by `begin_switch` has ended. Frames of the old source still queued
+    /// were dropped with it, so they cannot end up between those of the new
+    /// one. The new capture feeds the same encoded queues and media clock,
+    /// so tracks and peer connections stay up and viewers get a keyframe of
+    /// the new source instead of a black gap. The codec never changes, so no
+    /// renegotiation is needed.
+    pub fn switch_source(&mut self, id: &str, source_type: CaptureSourceType) -> Result<()> {
+        // Another preview may have claimed the source while the old capture
+        // drained, the stream then goes back to what it showed
+        let taken = self.stream_id_for(&source_type).filter(|other| other != id);
+
+        let mut stream = self
+            .active_streams
+            .get_mut(id)
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
+        // Fresh stop flag for the new session
+        let outputs = CaptureOutputs {
+            stop: Arc::new(AtomicBool::new(false)),
+            ..stream.outputs.clone()
+        };
+
+        if let Some(other) = taken {
+            let handle = self.spawn_capture(
+                stream.source_type.clone(),
+                stream.config.clone(),
+                outputs.clone(),
+            );
+            outputs.control.request_keyframe();
+            stream.outputs = outputs;
+            stream.capture_handle = Some(handle);
+            return Err(CaptureError::CaptureAlreadyActive(other));
+        }
+
+        // Annotations were drawn over the old source, and control was
+        // granted over it
+        outputs.annotations.clear();
+        remote::end_session(id, None, RevokeReason::SourceChanged);
+        *outputs.thumbnail.lock().unwrap() = None;
+
+        println!(
+            "[Capture] Switching {} from {} to {}",
+            id,
+            stream.source_type.to_id(),
+            source_type.to_id()
+        );
+        let handle =
+            self.spawn_capture(source_type.clone(), stream.config.clone(), outputs.clone());
+
+        // Viewers decode the new source from its first frame
+        outputs.control.request_keyframe();
+
+        stream.source_type = source_type;
+        stream.outputs = outputs;
+        stream.capture_handle = Some(handle);
+
+        Ok(())
+    }
+
+    /// Stream currently capturing `source_type`. Stream ids keep the source
+    /// they were started with, so they differ after a switch.
+    fn stream_id_for(&self, source_type: &CaptureSourceType) -> Option<String> {
+        self.active_streams
+            .iter()
+            .find(|stream| stream.source_type == *source_type)
+            .map(|stream| stream.id.clone())
+    }
+
     pub async fn stop_preview(&mut self, id: &str) -> Result<()> {
         if let Some((_, stream)) = self.active_streams.remove(id) {
+            remote::end_session(id, None, RevokeReason::Stopped);
+
+            // The blocking capture task cannot be aborted, it polls this flag
+            stream.outputs.stop.store(true, Ordering::Relaxed);
             if let Some(handle) = stream.capture_handle {
                 handle.abort();
             }
 
+            if let Some(camera) = &stream.outputs.camera {
+                camera.stop();
+            }
+
             crate::share_screen::WEBRTC_SERVER
                 .write()
                 .await
@@ -217,10 +383,93 @@ impl CaptureManager {
         Ok(())
     }
 
+    /// Live thumbnail of the source as PNG when it is already being shared,
+    /// so the picker does not capture it a second time. Empty otherwise.
+    fn live_thumbnail(&self, source_type: &CaptureSourceType) -> Vec<u8> {
+        self.stream_id_for(source_type)
+            .and_then(|id| self.active_streams.get(&id))
+            .and_then(|stream| stream.outputs.thumbnail.lock().unwrap().clone())
+            .and_then(|frame| {
+                thumbnail_png(frame)
+                    .inspect_err(|e| eprintln!("[Capture] Thumbnail error: {:?}", e))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn annotations(&self, id: &str) -> Result<Arc<AnnotationLayer>> {
+        self.active_streams
+            .get(id)
+            .map(|stream| stream.outputs.annotations.clone())
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))
+    }
+
+    /// Source the stream currently captures and its config.
+    pub fn stream_source(&self, id: &str) -> Result<(CaptureSourceType, CaptureConfig)> {
+        self.active_streams
+            .get(id)
+            .map(|stream| (stream.source_type.clone(), stream.config.clone()))
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))
+    }
+
+    /// Encoder side of the stream's stats, without viewers.
+    pub fn stream_stats(&self, id: &str) -> Result<StreamStats> {
+        let stream = self
+            .active_streams
+            .get(id)
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
+        let control = &stream.outputs.control;
+
+        let layers = stream
+            .outputs
+            .layers
+            .iter()
+            .map(|sink| {
+                let (width, height) = sink.counters.size();
+                let queue = sink.video_tx.stats();
+                let key = format!("{}/{}", id, sink.layer.rid);
+
+                LayerStats {
+                    rid: sink.layer.rid.to_string(),
+                    width,
+                    height,
+                    fps: stats::rate(format!("{}/frames", key), sink.counters.frames()),
+                    bitrate_kbps: stats::rate(format!("{}/bytes", key), sink.counters.bytes())
+                        * 8.0
+                        / 1000.0,
+                    keyframes: sink.counters.keyframes(),
+                    queue_depth: queue.depth,
+                    dropped_frames: queue.dropped,
+                }
+            })
+            .collect();
+
+        Ok(StreamStats {
+            id: id.to_string(),
+            target_kbps: control.target_kbps(),
+            target_fps: control.target_fps(),
+            layers,
+            viewers: Vec::new(),
+        })
+    }
+
+    /// Annotations drawn over `source_type`, whichever stream captures it.
+    pub fn annotations_for_source(
+        &self,
+        source_type: &CaptureSourceType,
+    ) -> Option<Arc<AnnotationLayer>> {
+        self.stream_id_for(source_type)
+            .and_then(|id| self.annotations(&id).ok())
+    }
+
     pub async fn get_audio_devices(&self) -> Result<Vec<AudioDevice>> {
         self.capture_device.enumerate_audio_devices()
     }
 
+    pub async fn get_cameras(&self) -> Result<Vec<CameraInfo>> {
+        self.capture_device.enumerate_cameras()
+    }
+
     async fn capture_single_frame_monitor(
         &self,
         hmonitor: isize,
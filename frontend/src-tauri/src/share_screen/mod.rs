@@ -1,8 +1,25 @@
 // src/share_screen/mod.rs
+pub mod annotation;
 pub mod capture;
 pub mod command;
+pub mod control;
+pub mod convert;
 pub mod dto;
+pub mod encoder;
+pub mod events;
+pub mod filter;
+pub mod frame;
 pub mod manager;
+pub mod pacer;
+pub mod pip;
+pub mod queue;
+pub mod remote;
+pub mod scale;
+pub mod settings;
+pub mod simulcast;
+pub mod static_content;
+pub mod stats;
+pub mod viewport;
 pub mod webrtc;
 
 use lazy_static::lazy_static;
@@ -0,0 +1,81 @@
+// src/share_screen/pacer.rs
+use std::time::{Duration, Instant};
+
+/// Fixed rate frame clock for the encoder, driven by a monotonic clock.
+///
+/// Each tick encodes the newest captured frame, or repeats the previous one
+/// when capture had nothing new, so the stream keeps its frame rate on an
+/// idle screen. Ticks missed because encoding ran late are dropped instead
+/// of being bunched up. Timestamps count from the stream's epoch rather than
+/// from when the pacer was made, so a stream keeps one media clock across
+/// capture sessions.
+pub struct FramePacer {
+    start: Instant,
+    interval: Duration,
+    next_tick: Instant,
+}
+
+impl FramePacer {
+    pub fn new(epoch: Instant, fps: u32) -> Self {
+        Self {
+            start: epoch,
+            interval: Duration::from_secs(1) / fps.max(1),
+            next_tick: Instant::now().max(epoch),
+        }
+    }
+
+    /// Changes the tick rate, starting from the tick after the next one.
+    pub fn set_fps(&mut self, fps: u32) {
+        self.interval = Duration::from_secs(1) / fps.max(1);
+    }
+
+    /// Makes the next tick due now, for when a slow rate must end at once.
+    pub fn wake(&mut self, now: Instant) {
+        self.next_tick = self.next_tick.min(now);
+    }
+
+    pub fn time_until_tick(&self, now: Instant) -> Duration {
+        self.next_tick.saturating_duration_since(now)
+    }
+
+    /// Returns the media timestamp of the due tick, if any. Timestamps are
+    /// the scheduled tick times, so jitter in when the caller polls does not
+    /// leak into the stream.
+    pub fn poll(&mut self, now: Instant) -> Option<Duration> {
+        if now < self.next_tick {
+            return None;
+        }
+
+        let tick = self.next_tick;
+        self.next_tick += self.interval;
+
+        if self.next_tick <= now {
+            let missed = ((now - self.next_tick).as_nanos() / self.interval.as_nanos()) as u32 + 1;
+            self.next_tick += self.interval * missed;
+        }
+
+        Some(tick - self.start)
+    }
+}
+
+/// Turns frame timestamps into sample durations for `write_sample`. Each
+/// sample is sent as soon as it is encoded, so its duration is the gap since
+/// the frame before it rather than the one after.
+#[derive(Default)]
+pub struct SampleClock {
+    previous: Option<Duration>,
+}
+
+impl SampleClock {
+    /// Duration to write for the sample stamped `timestamp`. The first sample
+    /// gets the `nominal` frame interval.
+    pub fn duration_for(&mut self, timestamp: Duration, nominal: Duration) -> Duration {
+        let gap = self
+            .previous
+            .map_or(nominal, |previous| timestamp.saturating_sub(previous));
+        self.previous = Some(timestamp);
+
+        // Equal RTP timestamps would merge two frames into one
+        gap.max(Duration::from_millis(1))
+    }
+}
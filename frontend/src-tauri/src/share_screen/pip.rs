@@ -0,0 +1,219 @@
+// src/share_screen/pip.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::filter::FrameFilter;
+use crate::share_screen::frame::{PixelFormat, VideoFrame};
+use crate::share_screen::scale::{FrameScaler, ScaleFilter};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipShape {
+    #[default]
+    Rectangle,
+    Rounded,
+    /// Center crop of the camera, square.
+    Circle,
+}
+
+/// Webcam picture-in-picture over the shared screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipConfig {
+    /// Camera id from `get_cameras`, the first camera when unset.
+    pub device_id: Option<String>,
+    pub corner: PipCorner,
+    /// Width of the camera box as a fraction of the frame width.
+    pub size: f32,
+    /// Gap to the frame edges, in output pixels.
+    pub margin: u32,
+    pub shape: PipShape,
+    pub border_width: u32,
+    /// RGBA.
+    pub border_color: [u8; 4],
+}
+
+impl Default for PipConfig {
+    fn default() -> Self {
+        Self {
+            device_id: None,
+            corner: PipCorner::default(),
+            size: 0.2,
+            margin: 16,
+            shape: PipShape::default(),
+            border_width: 3,
+            border_color: [255, 255, 255, 255],
+        }
+    }
+}
+
+/// Latest camera frame, BGRA, handed from the camera thread to the
+/// compositor.
+#[derive(Debug, Default)]
+pub struct CameraFeed {
+    latest: Mutex<Option<VideoFrame>>,
+    frames: AtomicU64,
+    stopped: AtomicBool,
+}
+
+impl CameraFeed {
+    pub fn publish(&self, frame: VideoFrame) {
+        *self.latest.lock().unwrap() = Some(frame);
+        self.frames.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn latest(&self) -> Option<VideoFrame> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frames.load(Ordering::Acquire)
+    }
+
+    /// Asks the camera thread to release the device.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// Composites the camera into a corner of the frame. Runs after the
+/// configured filters, so the box is sized against the frame that is encoded.
+pub struct PipFilter {
+    config: PipConfig,
+    feed: Arc<CameraFeed>,
+    scaler: FrameScaler,
+}
+
+impl PipFilter {
+    pub fn new(config: PipConfig, feed: Arc<CameraFeed>) -> Self {
+        Self {
+            config,
+            feed,
+            scaler: FrameScaler::new(ScaleFilter::Bilinear),
+        }
+    }
+
+    /// Camera scaled and center cropped to a `width` x `height` box.
+    fn camera_box(&mut self, camera: &VideoFrame, width: u32, height: u32) -> Result<VideoFrame> {
+        let (crop_w, crop_h) =
+            if camera.width as u64 * height as u64 > camera.height as u64 * width as u64 {
+                (
+                    (camera.height as u64 * width as u64 / height as u64) as u32,
+                    camera.height,
+                )
+            } else {
+                (
+                    camera.width,
+                    (camera.width as u64 * height as u64 / width as u64) as u32,
+                )
+            };
+        let left = (camera.width - crop_w) as usize / 2 * 4;
+        let top = (camera.height - crop_h) as usize / 2;
+
+        let mut data = Vec::with_capacity(crop_w as usize * crop_h as usize * 4);
+        for y in top..top + crop_h as usize {
+            data.extend_from_slice(&camera.row(0, y)[left..left + crop_w as usize * 4]);
+        }
+        let cropped = VideoFrame::bgra(crop_w, crop_h, crop_w as usize * 4, data)?;
+
+        self.scaler.scale_frame(&cropped, width, height)
+    }
+}
+
+impl FrameFilter for PipFilter {
+    fn name(&self) -> &'static str {
+        "pip"
+    }
+
+    fn revision(&self) -> u64 {
+        self.feed.frame_count()
+    }
+
+    fn apply(&mut self, mut frame: VideoFrame) -> Result<VideoFrame> {
+        let Some(camera) = self.feed.latest() else {
+            return Ok(frame);
+        };
+
+        if frame.format != PixelFormat::Bgra || camera.format != PixelFormat::Bgra {
+            return Err(CaptureError::ProcessingError(format!(
+                "Expected BGRA frames, got {:?} over {:?}",
+                camera.format, frame.format
+            )));
+        }
+
+        let width = ((frame.width as f32 * self.config.size.clamp(0.05, 1.0)) as u32)
+            .clamp(2, frame.width.max(2));
+        let height = match self.config.shape {
+            PipShape::Circle => width,
+            _ => (width as u64 * camera.height as u64 / camera.width.max(1) as u64) as u32,
+        }
+        .clamp(2, frame.height.max(2));
+
+        let margin = self.config.margin;
+        let x = match self.config.corner {
+            PipCorner::TopLeft | PipCorner::BottomLeft => margin,
+            PipCorner::TopRight | PipCorner::BottomRight => {
+                frame.width.saturating_sub(width + margin)
+            }
+        };
+        let y = match self.config.corner {
+            PipCorner::TopLeft | PipCorner::TopRight => margin,
+            PipCorner::BottomLeft | PipCorner::BottomRight => {
+                frame.height.saturating_sub(height + margin)
+            }
+        };
+
+        let scaled = self.camera_box(&camera, width, height)?;
+        let radius = match self.config.shape {
+            PipShape::Rectangle => 0.0,
+            PipShape::Rounded => width.min(height) as f32 / 8.0,
+            PipShape::Circle => width as f32 / 2.0,
+        };
+        let border = self.config.border_width as f32;
+        let [r, g, b, _] = self.config.border_color;
+
+        let stride = frame.strides[0];
+        let (half_w, half_h) = (width as f32 / 2.0, height as f32 / 2.0);
+
+        for by in 0..height.min(frame.height.saturating_sub(y)) as usize {
+            let dst = &mut frame.data[(y as usize + by) * stride..];
+            let src = scaled.row(0, by);
+
+            for bx in 0..width.min(frame.width.saturating_sub(x)) as usize {
+                // Signed distance to the rounded box edge, negative inside
+                let qx = (bx as f32 + 0.5 - half_w).abs() - (half_w - radius);
+                let qy = (by as f32 + 0.5 - half_h).abs() - (half_h - radius);
+                let outside = qx.max(0.0).hypot(qy.max(0.0));
+                let distance = outside + qx.max(qy).min(0.0) - radius;
+
+                if distance > 0.0 {
+                    continue;
+                }
+
+                let px = &mut dst[(x as usize + bx) * 4..(x as usize + bx) * 4 + 4];
+                if distance > -border {
+                    px[..3].copy_from_slice(&[b, g, r]);
+                } else {
+                    px[..3].copy_from_slice(&src[bx * 4..bx * 4 + 3]);
+                }
+            }
+        }
+
+        Ok(frame)
+    }
+}
@@ -0,0 +1,277 @@
+// src/share_screen/queue.rs
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// What a full queue does with the next frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Evict the oldest queued frame. For raw frames, where only the newest
+    /// one matters.
+    DropOldest,
+    /// Discard the incoming frame.
+    DropNewest,
+    /// Wait for room, then discard the incoming frame if none frees up.
+    Block(Duration),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueuePolicy {
+    pub capacity: usize,
+    pub drop: DropPolicy,
+}
+
+/// Captured frames waiting for the encoder. Stale frames are useless, so a
+/// slow encoder only ever sees the newest ones.
+pub const CAPTURE_QUEUE: QueuePolicy = QueuePolicy {
+    capacity: 2,
+    drop: DropPolicy::DropOldest,
+};
+
+/// Encoded frames waiting for the WebRTC writer. Losing one corrupts the
+/// picture until the next keyframe, so the encoder waits a little first.
+pub const ENCODED_QUEUE: QueuePolicy = QueuePolicy {
+    capacity: 30,
+    drop: DropPolicy::Block(Duration::from_millis(100)),
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueueStats {
+    pub queued: u64,
+    pub dropped: u64,
+    /// Items waiting right now.
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendOutcome {
+    Queued,
+    /// The frame went in but a frame was lost on the way, either this one or
+    /// an older one that was evicted.
+    Dropped,
+}
+
+/// The other end of the queue is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+struct State<T> {
+    items: VecDeque<T>,
+    receiver_alive: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    notify: Notify,
+    senders: AtomicUsize,
+    policy: QueuePolicy,
+    queued: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl<T> Shared<T> {
+    fn stats(&self) -> QueueStats {
+        QueueStats {
+            queued: self.queued.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            depth: self.state.lock().unwrap().items.len(),
+        }
+    }
+}
+
+/// Bounded single consumer queue between pipeline stages. Unlike the std and
+/// tokio channels it applies a `DropPolicy` when full, counts what it drops,
+/// and can be drained from both threads and async tasks.
+pub fn frame_queue<T>(policy: QueuePolicy) -> (QueueSender<T>, QueueReceiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            items: VecDeque::with_capacity(policy.capacity),
+            receiver_alive: true,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        policy,
+        queued: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+    });
+
+    (
+        QueueSender {
+            shared: shared.clone(),
+        },
+        QueueReceiver { shared },
+    )
+}
+
+pub struct QueueSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> QueueSender<T> {
+    pub fn send(&self, item: T) -> Result<SendOutcome, Closed> {
+        let shared = &self.shared;
+        let mut state = shared.state.lock().unwrap();
+        let mut outcome = SendOutcome::Queued;
+
+        if !state.receiver_alive {
+            return Err(Closed);
+        }
+
+        if state.items.len() >= shared.policy.capacity {
+            match shared.policy.drop {
+                DropPolicy::DropOldest => {
+                    state.items.pop_front();
+                    outcome = SendOutcome::Dropped;
+                }
+                DropPolicy::DropNewest => {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(SendOutcome::Dropped);
+                }
+                DropPolicy::Block(timeout) => {
+                    let deadline = Instant::now() + timeout;
+                    while state.receiver_alive && state.items.len() >= shared.policy.capacity {
+                        let left = deadline.saturating_duration_since(Instant::now());
+                        if left.is_zero() {
+                            break;
+                        }
+                        state = shared.not_full.wait_timeout(state, left).unwrap().0;
+                    }
+
+                    if !state.receiver_alive {
+                        return Err(Closed);
+                    }
+                    if state.items.len() >= shared.policy.capacity {
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return Ok(SendOutcome::Dropped);
+                    }
+                }
+            }
+        }
+
+        if outcome == SendOutcome::Dropped {
+            shared.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        shared.queued.fetch_add(1, Ordering::Relaxed);
+
+        state.items.push_back(item);
+        drop(state);
+
+        shared.not_empty.notify_one();
+        shared.notify.notify_one();
+
+        Ok(outcome)
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+}
+
+impl<T> fmt::Debug for QueueSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueSender")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for QueueSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Take the lock so a receiver between its check and its wait
+            // cannot miss the wakeup
+            drop(self.shared.state.lock().unwrap());
+            self.shared.not_empty.notify_all();
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+pub struct QueueReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> QueueReceiver<T> {
+    fn disconnected(&self) -> bool {
+        self.shared.senders.load(Ordering::Acquire) == 0
+    }
+
+    fn pop(&self, state: &mut State<T>) -> Option<T> {
+        let item = state.items.pop_front()?;
+        self.shared.not_full.notify_one();
+        Some(item)
+    }
+
+    /// Blocking receive for pipeline threads.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state.lock().unwrap();
+
+        loop {
+            if let Some(item) = self.pop(&mut state) {
+                return Ok(item);
+            }
+            if self.disconnected() {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = self.shared.not_empty.wait_timeout(state, left).unwrap().0;
+        }
+    }
+
+    /// Async receive. Returns `None` once every sender is gone and the queue
+    /// is drained.
+    pub async fn recv(&self) -> Option<T> {
+        loop {
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(item) = self.pop(&mut state) {
+                    return Some(item);
+                }
+                if self.disconnected() {
+                    return None;
+                }
+            }
+
+            // notify_one keeps a permit, so a send between the check and
+            // here is not lost
+            self.shared.notify.notified().await;
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        self.shared.stats()
+    }
+}
+
+impl<T> Drop for QueueReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.receiver_alive = false;
+        state.items.clear();
+        drop(state);
+
+        self.shared.not_full.notify_all();
+    }
+}
@@ -0,0 +1,1043 @@
+// src/share_screen/remote/mod.rs
+#[cfg(windows)]
+pub mod windows;
+
+use crate::share_screen::control::ControlMessage;
+use crate::share_screen::dto::{CaptureConfig, CaptureError, CaptureSourceType, Result};
+use crate::share_screen::events;
+use crate::share_screen::filter::FilterConfig;
+use crate::share_screen::{MANAGER, WEBRTC_SERVER};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// Held by the local user, ends remote control at once.
+pub const REVOKE_HOTKEY: &str = "Ctrl+Alt+Escape";
+
+const HOTKEY_POLL: Duration = Duration::from_millis(50);
+const AUDIT_FILE: &str = "remote-control.log";
+
+lazy_static! {
+    pub static ref REMOTE_CONTROL: Mutex<RemoteControl> =
+        Mutex::new(RemoteControl::new(platform_injector()));
+}
+
+/// Viewer input. Positions are fractions of the stream, 0 to 1 from the
+/// top left, so they do not depend on the layer the viewer receives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteInput {
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseButton {
+        button: MouseButton,
+        down: bool,
+        x: f32,
+        y: f32,
+    },
+    /// Deltas in wheel notches, positive is right and down as on the web.
+    Wheel {
+        x: f32,
+        y: f32,
+        delta_x: f32,
+        delta_y: f32,
+    },
+    /// `code` is the web `KeyboardEvent.code`, such as `KeyA` or `Enter`.
+    Key {
+        code: String,
+        down: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+/// Input in screen pixels, ready for an `InputInjector`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreenInput {
+    MoveTo { x: i32, y: i32 },
+    Button { button: MouseButton, down: bool },
+    Wheel { delta_x: f32, delta_y: f32 },
+    Key { code: String, down: bool },
+}
+
+/// Screen area a source covers, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenRect {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl ScreenRect {
+    /// Screen pixel for a position given as a fraction of the stream,
+    /// `None` outside of it.
+    pub fn to_screen(&self, x: f32, y: f32) -> Option<(i32, i32)> {
+        if !(0.0..=1.0).contains(&x)
+            || !(0.0..=1.0).contains(&y)
+            || self.width <= 0
+            || self.height <= 0
+        {
+            return None;
+        }
+
+        let px = ((x * self.width as f32) as i32).min(self.width - 1);
+        let py = ((y * self.height as f32) as i32).min(self.height - 1);
+        Some((self.left + px, self.top + py))
+    }
+}
+
+/// Screen inputs for a viewer's input on a source covering `rect`. `None`
+/// when it points outside the stream.
+pub fn map_input(input: &RemoteInput, rect: ScreenRect) -> Option<Vec<ScreenInput>> {
+    let move_to = |(x, y)| ScreenInput::MoveTo { x, y };
+
+    match input {
+        RemoteInput::MouseMove { x, y } => Some(vec![move_to(rect.to_screen(*x, *y)?)]),
+        RemoteInput::MouseButton { button, down, x, y } => {
+            let at = rect.to_screen(*x, *y);
+            // A release outside still has to happen, or the button stays down
+            if at.is_none() && *down {
+                return None;
+            }
+            Some(
+                at.map(move_to)
+                    .into_iter()
+                    .chain([ScreenInput::Button {
+                        button: *button,
+                        down: *down,
+                    }])
+                    .collect(),
+            )
+        }
+        RemoteInput::Wheel {
+            x,
+            y,
+            delta_x,
+            delta_y,
+        } => Some(vec![
+            move_to(rect.to_screen(*x, *y)?),
+            ScreenInput::Wheel {
+                delta_x: *delta_x,
+                delta_y: *delta_y,
+            },
+        ]),
+        RemoteInput::Key { code, down } => Some(vec![ScreenInput::Key {
+            code: code.clone(),
+            down: *down,
+        }]),
+    }
+}
+
+// Positions map linearly onto the source only while the stream shows all
+// of it
+fn check_mappable(config: &CaptureConfig) -> Result<()> {
+    let reframed = config.zoom.is_some()
+        || config
+            .filters
+            .iter()
+            .any(|filter| matches!(filter, FilterConfig::Crop { .. } | FilterConfig::Pad { .. }));
+
+    if reframed {
+        return Err(CaptureError::InvalidConfig(
+            "Remote control needs the whole source in the stream, without zoom, crop or pad"
+                .to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Platform side of remote control. `RecordingInjector` stands in for it
+/// to exercise the protocol, mapping and gating without real input.
+pub trait InputInjector: Send {
+    /// Screen area the source covers right now, windows move.
+    fn source_rect(&self, source: &CaptureSourceType) -> Result<ScreenRect>;
+
+    /// Whether input at `at`, or keyboard input when unset, lands in the
+    /// source. Other windows may cover a shared window or hold the focus.
+    fn reaches_source(&self, source: &CaptureSourceType, at: Option<(i32, i32)>) -> bool;
+
+    fn inject(&mut self, input: &ScreenInput) -> Result<()>;
+
+    /// Whether the local user holds `REVOKE_HOTKEY`.
+    fn revoke_hotkey_down(&self) -> bool;
+}
+
+#[cfg(windows)]
+fn platform_injector() -> Box<dyn InputInjector> {
+    Box::new(windows::SendInputInjector)
+}
+
+#[cfg(not(windows))]
+fn platform_injector() -> Box<dyn InputInjector> {
+    Box::new(UnsupportedInjector)
+}
+
+#[cfg(not(windows))]
+struct UnsupportedInjector;
+
+#[cfg(not(windows))]
+impl InputInjector for UnsupportedInjector {
+    fn source_rect(&self, _source: &CaptureSourceType) -> Result<ScreenRect> {
+        Err(CaptureError::PlatformError("Not supported".to_string()))
+    }
+
+    fn reaches_source(&self, _source: &CaptureSourceType, _at: Option<(i32, i32)>) -> bool {
+        false
+    }
+
+    fn inject(&mut self, _input: &ScreenInput) -> Result<()> {
+        Err(CaptureError::PlatformError("Not supported".to_string()))
+    }
+
+    fn revoke_hotkey_down(&self) -> bool {
+        false
+    }
+}
+
+/// Records inputs instead of injecting them. Clones share their state, so
+/// a test keeps one while `RemoteControl` owns the other.
+#[derive(Debug, Clone)]
+pub struct RecordingInjector {
+    pub rect: ScreenRect,
+    pub in_front: Arc<AtomicBool>,
+    pub hotkey: Arc<AtomicBool>,
+    pub inputs: Arc<Mutex<Vec<ScreenInput>>>,
+}
+
+impl RecordingInjector {
+    pub fn new(rect: ScreenRect) -> Self {
+        Self {
+            rect,
+            in_front: Arc::new(AtomicBool::new(true)),
+            hotkey: Arc::new(AtomicBool::new(false)),
+            inputs: Arc::default(),
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<ScreenInput> {
+        self.inputs.lock().unwrap().clone()
+    }
+}
+
+impl InputInjector for RecordingInjector {
+    fn source_rect(&self, _source: &CaptureSourceType) -> Result<ScreenRect> {
+        Ok(self.rect)
+    }
+
+    fn reaches_source(&self, _source: &CaptureSourceType, _at: Option<(i32, i32)>) -> bool {
+        self.in_front.load(Ordering::Relaxed)
+    }
+
+    fn inject(&mut self, input: &ScreenInput) -> Result<()> {
+        self.inputs.lock().unwrap().push(input.clone());
+        Ok(())
+    }
+
+    fn revoke_hotkey_down(&self) -> bool {
+        self.hotkey.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevokeReason {
+    /// The sharer took control back.
+    Sharer,
+    Hotkey,
+    /// The viewer gave control back.
+    Released,
+    /// Another viewer was granted control.
+    Replaced,
+    SourceChanged,
+    Stopped,
+    Disconnected,
+}
+
+/// Step of a remote control session. Sent to the frontend, to the viewer
+/// for the sharer's decisions, and written to the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum RemoteControlChange {
+    /// The viewer asked for control, the sharer has to answer.
+    Requested,
+    Granted,
+    Denied,
+    Revoked {
+        reason: RevokeReason,
+        events: u64,
+    },
+    /// Input from a viewer without control. Logged once per viewer.
+    InputRefused,
+}
+
+impl RemoteControlChange {
+    fn tells_viewer(&self) -> bool {
+        matches!(
+            self,
+            RemoteControlChange::Granted
+                | RemoteControlChange::Denied
+                | RemoteControlChange::Revoked { .. }
+        )
+    }
+}
+
+/// Payload of `REMOTE_CONTROL`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteControlChanged {
+    pub id: String,
+    pub viewer_id: String,
+    #[serde(flatten)]
+    pub change: RemoteControlChange,
+}
+
+/// Append only record of every remote control decision, one JSON line
+/// each. Without a file it only goes to stdout.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    at_ms: u128,
+    #[serde(flatten)]
+    changed: &'a RemoteControlChanged,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    pub fn record(&self, changed: &RemoteControlChanged) {
+        let entry = AuditEntry {
+            at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            changed,
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        println!("[Remote] {}", line);
+
+        if let Some(path) = &self.path {
+            let written = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(e) = written {
+                eprintln!("[Remote] Failed to write {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+struct ControlSession {
+    serial: u64,
+    id: String,
+    viewer_id: String,
+    source: CaptureSourceType,
+    events: u64,
+    // Released when the session ends, the viewer may not get to it
+    held_keys: HashSet<String>,
+    held_buttons: HashSet<MouseButton>,
+}
+
+/// Consent gate between viewers asking for control and the injector. Only
+/// a viewer the sharer granted control injects, one at a time, until the
+/// sharer, the hotkey or the end of the stream revokes it.
+pub struct RemoteControl {
+    injector: Box<dyn InputInjector>,
+    audit: AuditLog,
+    /// Unanswered requests as (id, viewer_id).
+    requests: HashSet<(String, String)>,
+    refused: HashSet<(String, String)>,
+    session: Option<ControlSession>,
+    next_serial: u64,
+}
+
+impl RemoteControl {
+    pub fn new(injector: Box<dyn InputInjector>) -> Self {
+        Self {
+            injector,
+            audit: AuditLog::default(),
+            requests: HashSet::new(),
+            refused: HashSet::new(),
+            session: None,
+            next_serial: 0,
+        }
+    }
+
+    pub fn set_audit_log(&mut self, audit: AuditLog) {
+        self.audit = audit;
+    }
+
+    /// Identifies the running session, a new grant gets a new one.
+    pub fn serial(&self) -> Option<u64> {
+        self.session.as_ref().map(|session| session.serial)
+    }
+
+    fn change(
+        &self,
+        id: &str,
+        viewer_id: &str,
+        change: RemoteControlChange,
+    ) -> RemoteControlChanged {
+        let changed = RemoteControlChanged {
+            id: id.to_string(),
+            viewer_id: viewer_id.to_string(),
+            change,
+        };
+        self.audit.record(&changed);
+        changed
+    }
+
+    pub fn request(&mut self, id: &str, viewer_id: &str) -> RemoteControlChanged {
+        self.requests
+            .insert((id.to_string(), viewer_id.to_string()));
+        self.change(id, viewer_id, RemoteControlChange::Requested)
+    }
+
+    /// The sharer's consent to a pending request. Takes control from any
+    /// other viewer.
+    pub fn grant(
+        &mut self,
+        id: &str,
+        viewer_id: &str,
+        source: CaptureSourceType,
+        config: &CaptureConfig,
+    ) -> Result<Vec<RemoteControlChanged>> {
+        let key = (id.to_string(), viewer_id.to_string());
+        if !self.requests.contains(&key) {
+            return Err(CaptureError::PermissionDenied(format!(
+                "{} on {} did not ask for control",
+                viewer_id, id
+            )));
+        }
+        check_mappable(config)?;
+        self.injector.source_rect(&source)?;
+
+        self.requests.remove(&key);
+        self.refused.remove(&key);
+        let mut changes: Vec<_> = self.revoke(RevokeReason::Replaced).into_iter().collect();
+
+        self.next_serial += 1;
+        self.session = Some(ControlSession {
+            serial: self.next_serial,
+            id: id.to_string(),
+            viewer_id: viewer_id.to_string(),
+            source,
+            events: 0,
+            held_keys: HashSet::new(),
+            held_buttons: HashSet::new(),
+        });
+        changes.push(self.change(id, viewer_id, RemoteControlChange::Granted));
+
+        Ok(changes)
+    }
+
+    pub fn deny(&mut self, id: &str, viewer_id: &str) -> Result<RemoteControlChanged> {
+        if !self
+            .requests
+            .remove(&(id.to_string(), viewer_id.to_string()))
+        {
+            return Err(CaptureError::PermissionDenied(format!(
+                "{} on {} did not ask for control",
+                viewer_id, id
+            )));
+        }
+
+        Ok(self.change(id, viewer_id, RemoteControlChange::Denied))
+    }
+
+    /// Ends the running session, if any.
+    pub fn revoke(&mut self, reason: RevokeReason) -> Option<RemoteControlChanged> {
+        let session = self.session.take()?;
+
+        let releases = session
+            .held_buttons
+            .iter()
+            .map(|&button| ScreenInput::Button {
+                button,
+                down: false,
+            })
+            .chain(session.held_keys.iter().map(|code| ScreenInput::Key {
+                code: code.clone(),
+                down: false,
+            }));
+        for input in releases {
+            if let Err(e) = self.injector.inject(&input) {
+                eprintln!("[Remote] Failed to release {:?}: {:?}", input, e);
+            }
+        }
+
+        Some(self.change(
+            &session.id,
+            &session.viewer_id,
+            RemoteControlChange::Revoked {
+                reason,
+                events: session.events,
+            },
+        ))
+    }
+
+    /// Ends the session of the stream, or of one of its viewers, and drops
+    /// their requests.
+    pub fn end_session(
+        &mut self,
+        id: &str,
+        viewer_id: Option<&str>,
+        reason: RevokeReason,
+    ) -> Option<RemoteControlChanged> {
+        let matches = |request_id: &str, request_viewer: &str| {
+            request_id == id && viewer_id.is_none_or(|viewer_id| viewer_id == request_viewer)
+        };
+        self.requests.retain(|(i, v)| !matches(i, v));
+
+        let active = self
+            .session
+            .as_ref()
+            .is_some_and(|session| matches(&session.id, &session.viewer_id));
+        if active { self.revoke(reason) } else { None }
+    }
+
+    pub fn poll_hotkey(&mut self) -> Option<RemoteControlChanged> {
+        if self.session.is_some() && self.injector.revoke_hotkey_down() {
+            return self.revoke(RevokeReason::Hotkey);
+        }
+        None
+    }
+
+    /// Injects input from a viewer that has control. Input pointing outside
+    /// the stream, or at a shared window that is covered or not focused, is
+    /// dropped, except releases of what the viewer holds down.
+    pub fn handle_input(
+        &mut self,
+        id: &str,
+        viewer_id: &str,
+        input: &RemoteInput,
+    ) -> Result<Option<RemoteControlChanged>> {
+        if let Some(revoked) = self.poll_hotkey() {
+            return Ok(Some(revoked));
+        }
+
+        let Some(session) = self
+            .session
+            .as_mut()
+            .filter(|session| session.id == id && session.viewer_id == viewer_id)
+        else {
+            let first = self.refused.insert((id.to_string(), viewer_id.to_string()));
+            return Ok(first.then(|| self.change(id, viewer_id, RemoteControlChange::InputRefused)));
+        };
+
+        let rect = self.injector.source_rect(&session.source)?;
+        let Some(inputs) = map_input(input, rect) else {
+            return Ok(None);
+        };
+
+        let release = match input {
+            RemoteInput::MouseButton {
+                button,
+                down: false,
+                ..
+            } => session.held_buttons.contains(button),
+            RemoteInput::Key { code, down: false } => session.held_keys.contains(code),
+            _ => false,
+        };
+        let at = inputs.iter().find_map(|input| match *input {
+            ScreenInput::MoveTo { x, y } => Some((x, y)),
+            _ => None,
+        });
+        if !release && !self.injector.reaches_source(&session.source, at) {
+            return Ok(None);
+        }
+
+        for input in &inputs {
+            self.injector.inject(input)?;
+            match input {
+                ScreenInput::Button { button, down: true } => {
+                    session.held_buttons.insert(*button);
+                }
+                ScreenInput::Button {
+                    button,
+                    down: false,
+                } => {
+                    session.held_buttons.remove(button);
+                }
+                ScreenInput::Key { code, down: true } => {
+                    session.held_keys.insert(code.clone());
+                }
+                ScreenInput::Key { code, down: false } => {
+                    session.held_keys.remove(code);
+                }
+                _ => {}
+            }
+        }
+        session.events += 1;
+
+        Ok(None)
+    }
+}
+
+/// Points the audit log at the app log dir. Called from the app setup.
+pub fn setup(app: &AppHandle) {
+    let dir = match app.path().app_log_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("[Remote] Audit log only on stdout: {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("[Remote] Audit log only on stdout: {:?}", e);
+        return;
+    }
+
+    REMOTE_CONTROL
+        .lock()
+        .unwrap()
+        .set_audit_log(AuditLog::new(dir.join(AUDIT_FILE)));
+}
+
+// Tells the frontend, and the viewer about the sharer's decisions
+fn publish(changed: RemoteControlChanged) {
+    events::emit(events::REMOTE_CONTROL, changed.clone());
+
+    if changed.change.tells_viewer() {
+        tokio::spawn(async move {
+            let message = ControlMessage::RemoteControl(changed.change);
+            let _ = WEBRTC_SERVER
+                .read()
+                .await
+                .send_control(&changed.id, &changed.viewer_id, &message)
+                .await;
+        });
+    }
+}
+
+pub fn request(id: &str, viewer_id: &str) {
+    let changed = REMOTE_CONTROL.lock().unwrap().request(id, viewer_id);
+    publish(changed);
+}
+
+pub async fn grant(id: &str, viewer_id: &str) -> Result<()> {
+    let (source, config) = MANAGER.read().await.stream_source(id)?;
+
+    let (changes, serial) = {
+        let mut control = REMOTE_CONTROL.lock().unwrap();
+        let changes = control.grant(id, viewer_id, source, &config)?;
+        (changes, control.serial())
+    };
+    changes.into_iter().for_each(publish);
+
+    if let Some(serial) = serial {
+        watch_hotkey(serial);
+    }
+    Ok(())
+}
+
+pub fn deny(id: &str, viewer_id: &str) -> Result<()> {
+    let changed = REMOTE_CONTROL.lock().unwrap().deny(id, viewer_id)?;
+    publish(changed);
+    Ok(())
+}
+
+pub fn revoke(reason: RevokeReason) {
+    let revoked = REMOTE_CONTROL.lock().unwrap().revoke(reason);
+    if let Some(changed) = revoked {
+        publish(changed);
+    }
+}
+
+pub fn end_session(id: &str, viewer_id: Option<&str>, reason: RevokeReason) {
+    let revoked = REMOTE_CONTROL
+        .lock()
+        .unwrap()
+        .end_session(id, viewer_id, reason);
+    if let Some(changed) = revoked {
+        publish(changed);
+    }
+}
+
+/// Runs on the data channel's own task, so input stays in order.
+pub fn input(id: &str, viewer_id: &str, input: &RemoteInput) {
+    let handled = REMOTE_CONTROL
+        .lock()
+        .unwrap()
+        .handle_input(id, viewer_id, input);
+    match handled {
+        Ok(Some(changed)) => publish(changed),
+        Ok(None) => {}
+        Err(e) => eprintln!("[Remote] {} input from {} failed: {:?}", id, viewer_id, e),
+    }
+}
+
+// The hotkey also works while the viewer sends nothing
+fn watch_hotkey(serial: u64) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(HOTKEY_POLL).await;
+
+            let revoked = {
+                let mut control = REMOTE_CONTROL.lock().unwrap();
+                if control.serial() != Some(serial) {
+                    break;
+                }
+                control.poll_hotkey()
+            };
+            if let Some(changed) = revoked {
+                publish(changed);
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share_screen::viewport::ZoomConfig;
+
+    const RECT: ScreenRect = ScreenRect {
+        left: 100,
+        top: 50,
+        width: 1000,
+        height: 500,
+    };
+    const SOURCE: CaptureSourceType = CaptureSourceType::Monitor(1);
+
+    fn control() -> (RecordingInjector, RemoteControl) {
+        let injector = RecordingInjector::new(RECT);
+        let control = RemoteControl::new(Box::new(injector.clone()));
+        (injector, control)
+    }
+
+    // A control session of viewer "v1" on stream "s"
+    fn granted() -> (RecordingInjector, RemoteControl) {
+        let (injector, mut control) = control();
+        control.request("s", "v1");
+        control
+            .grant("s", "v1", SOURCE, &CaptureConfig::default())
+            .unwrap();
+        (injector, control)
+    }
+
+    fn button(button: MouseButton, down: bool, x: f32, y: f32) -> RemoteInput {
+        RemoteInput::MouseButton { button, down, x, y }
+    }
+
+    fn key(code: &str, down: bool) -> RemoteInput {
+        RemoteInput::Key {
+            code: code.to_string(),
+            down,
+        }
+    }
+
+    #[test]
+    fn screen_rect_maps_fractions_inside_the_source() {
+        assert_eq!(RECT.to_screen(0.0, 0.0), Some((100, 50)));
+        assert_eq!(RECT.to_screen(0.5, 0.5), Some((600, 300)));
+        // The far edges stay on the last pixel
+        assert_eq!(RECT.to_screen(1.0, 1.0), Some((1099, 549)));
+
+        assert_eq!(RECT.to_screen(-0.1, 0.5), None);
+        assert_eq!(RECT.to_screen(0.5, 1.1), None);
+        assert_eq!(RECT.to_screen(f32::NAN, 0.5), None);
+
+        let empty = ScreenRect { width: 0, ..RECT };
+        assert_eq!(empty.to_screen(0.5, 0.5), None);
+    }
+
+    #[test]
+    fn map_input_moves_before_clicks_and_wheels() {
+        assert_eq!(
+            map_input(&RemoteInput::MouseMove { x: 0.5, y: 0.5 }, RECT),
+            Some(vec![ScreenInput::MoveTo { x: 600, y: 300 }])
+        );
+        assert_eq!(
+            map_input(&button(MouseButton::Right, true, 0.0, 1.0), RECT),
+            Some(vec![
+                ScreenInput::MoveTo { x: 100, y: 549 },
+                ScreenInput::Button {
+                    button: MouseButton::Right,
+                    down: true,
+                },
+            ])
+        );
+        assert_eq!(
+            map_input(
+                &RemoteInput::Wheel {
+                    x: 0.1,
+                    y: 0.2,
+                    delta_x: 0.0,
+                    delta_y: -1.0,
+                },
+                RECT
+            ),
+            Some(vec![
+                ScreenInput::MoveTo { x: 200, y: 150 },
+                ScreenInput::Wheel {
+                    delta_x: 0.0,
+                    delta_y: -1.0,
+                },
+            ])
+        );
+        assert_eq!(
+            map_input(&key("KeyA", true), RECT),
+            Some(vec![ScreenInput::Key {
+                code: "KeyA".to_string(),
+                down: true,
+            }])
+        );
+    }
+
+    #[test]
+    fn map_input_drops_outside_except_releases() {
+        assert_eq!(
+            map_input(&RemoteInput::MouseMove { x: 1.5, y: 0.5 }, RECT),
+            None
+        );
+        assert_eq!(
+            map_input(&button(MouseButton::Left, true, 1.5, 0.5), RECT),
+            None
+        );
+        assert_eq!(
+            map_input(&button(MouseButton::Left, false, 1.5, 0.5), RECT),
+            Some(vec![ScreenInput::Button {
+                button: MouseButton::Left,
+                down: false,
+            }])
+        );
+    }
+
+    #[test]
+    fn grant_needs_a_pending_request() {
+        let (_, mut control) = control();
+        let config = CaptureConfig::default();
+
+        assert!(matches!(
+            control.grant("s", "v1", SOURCE, &config),
+            Err(CaptureError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            control.deny("s", "v1"),
+            Err(CaptureError::PermissionDenied(_))
+        ));
+
+        // A request is answered once
+        control.request("s", "v1");
+        control.deny("s", "v1").unwrap();
+        assert!(matches!(
+            control.grant("s", "v1", SOURCE, &config),
+            Err(CaptureError::PermissionDenied(_))
+        ));
+        assert_eq!(control.serial(), None);
+    }
+
+    #[test]
+    fn grant_needs_the_whole_source() {
+        let (_, mut control) = control();
+        control.request("s", "v1");
+
+        let zoomed = CaptureConfig {
+            zoom: Some(ZoomConfig::default()),
+            ..CaptureConfig::default()
+        };
+        let cropped = CaptureConfig {
+            filters: vec![FilterConfig::Crop {
+                x: 0,
+                y: 0,
+                width: 640,
+                height: 360,
+            }],
+            ..CaptureConfig::default()
+        };
+        for config in [zoomed, cropped] {
+            assert!(matches!(
+                control.grant("s", "v1", SOURCE, &config),
+                Err(CaptureError::InvalidConfig(_))
+            ));
+        }
+
+        // Still pending after the refusals
+        let changes = control
+            .grant("s", "v1", SOURCE, &CaptureConfig::default())
+            .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change, RemoteControlChange::Granted);
+    }
+
+    #[test]
+    fn refused_input_is_logged_once_per_viewer() {
+        let (injector, mut control) = granted();
+        let input = RemoteInput::MouseMove { x: 0.5, y: 0.5 };
+
+        let first = control.handle_input("s", "v2", &input).unwrap();
+        assert_eq!(
+            first.map(|changed| changed.change),
+            Some(RemoteControlChange::InputRefused)
+        );
+        assert!(control.handle_input("s", "v2", &input).unwrap().is_none());
+        assert!(control.handle_input("s", "v2", &input).unwrap().is_none());
+
+        // Another viewer gets its own entry
+        assert!(control.handle_input("s", "v3", &input).unwrap().is_some());
+        assert!(injector.inputs().is_empty());
+    }
+
+    #[test]
+    fn input_is_injected_while_it_reaches_the_source() {
+        let (injector, mut control) = granted();
+
+        control
+            .handle_input("s", "v1", &RemoteInput::MouseMove { x: 0.5, y: 0.5 })
+            .unwrap();
+        injector.in_front.store(false, Ordering::Relaxed);
+        control
+            .handle_input("s", "v1", &RemoteInput::MouseMove { x: 0.1, y: 0.1 })
+            .unwrap();
+        control.handle_input("s", "v1", &key("KeyA", true)).unwrap();
+
+        assert_eq!(
+            injector.inputs(),
+            vec![ScreenInput::MoveTo { x: 600, y: 300 }]
+        );
+    }
+
+    #[test]
+    fn revoke_releases_held_keys_and_buttons() {
+        let (injector, mut control) = granted();
+
+        control
+            .handle_input("s", "v1", &button(MouseButton::Left, true, 0.5, 0.5))
+            .unwrap();
+        control
+            .handle_input("s", "v1", &key("ShiftLeft", true))
+            .unwrap();
+        control.handle_input("s", "v1", &key("KeyA", true)).unwrap();
+        control
+            .handle_input("s", "v1", &key("KeyA", false))
+            .unwrap();
+        injector.inputs.lock().unwrap().clear();
+
+        let revoked = control.revoke(RevokeReason::Sharer).unwrap();
+        assert_eq!(
+            revoked.change,
+            RemoteControlChange::Revoked {
+                reason: RevokeReason::Sharer,
+                events: 4,
+            }
+        );
+
+        let released = injector.inputs();
+        assert_eq!(released.len(), 2);
+        assert!(released.contains(&ScreenInput::Button {
+            button: MouseButton::Left,
+            down: false,
+        }));
+        assert!(released.contains(&ScreenInput::Key {
+            code: "ShiftLeft".to_string(),
+            down: false,
+        }));
+
+        assert_eq!(control.serial(), None);
+        assert!(control.revoke(RevokeReason::Sharer).is_none());
+    }
+
+    #[test]
+    fn held_releases_pass_when_the_source_is_covered() {
+        let (injector, mut control) = granted();
+
+        control
+            .handle_input("s", "v1", &button(MouseButton::Left, true, 0.5, 0.5))
+            .unwrap();
+        injector.in_front.store(false, Ordering::Relaxed);
+        control
+            .handle_input("s", "v1", &button(MouseButton::Left, false, 2.0, 0.5))
+            .unwrap();
+        injector.inputs.lock().unwrap().clear();
+
+        // Nothing is left to release
+        control.revoke(RevokeReason::Sharer);
+        assert!(injector.inputs().is_empty());
+    }
+
+    #[test]
+    fn hotkey_revokes_through_poll() {
+        let (injector, mut control) = granted();
+        control.handle_input("s", "v1", &key("KeyB", true)).unwrap();
+
+        assert!(control.poll_hotkey().is_none());
+        assert!(control.serial().is_some());
+
+        injector.hotkey.store(true, Ordering::Relaxed);
+        let revoked = control.poll_hotkey().unwrap();
+        assert_eq!(
+            revoked.change,
+            RemoteControlChange::Revoked {
+                reason: RevokeReason::Hotkey,
+                events: 1,
+            }
+        );
+        assert_eq!(
+            injector.inputs().last(),
+            Some(&ScreenInput::Key {
+                code: "KeyB".to_string(),
+                down: false,
+            })
+        );
+        assert_eq!(control.serial(), None);
+
+        // Input after the revoke is refused
+        let after = control.handle_input("s", "v1", &key("KeyC", true)).unwrap();
+        assert_eq!(
+            after.map(|changed| changed.change),
+            Some(RemoteControlChange::InputRefused)
+        );
+        assert!(control.poll_hotkey().is_none());
+    }
+
+    #[test]
+    fn end_session_only_revokes_the_matching_viewer() {
+        let (_, mut control) = granted();
+        control.request("s", "v2");
+
+        assert!(
+            control
+                .end_session("s", Some("v2"), RevokeReason::Disconnected)
+                .is_none()
+        );
+        assert!(control.serial().is_some());
+        // v2's request went with it
+        assert!(
+            control
+                .grant("s", "v2", SOURCE, &CaptureConfig::default())
+                .is_err()
+        );
+
+        let revoked = control.end_session("s", None, RevokeReason::Stopped);
+        assert!(revoked.is_some());
+        assert_eq!(control.serial(), None);
+    }
+}
@@ -0,0 +1,281 @@
+// src/share_screen/remote/windows.rs
+use super::{InputInjector, MouseButton, ScreenInput, ScreenRect};
+use crate::share_screen::dto::{CaptureError, CaptureSourceType, Result};
+use windows::Win32::Foundation::{HWND, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, HMONITOR, MONITORINFO};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC, MOUSE_EVENT_FLAGS,
+    MOUSEEVENTF_ABSOLUTE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN,
+    MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
+    MOUSEEVENTF_XUP, MOUSEINPUT, MapVirtualKeyW, SendInput, VIRTUAL_KEY, VK_CONTROL, VK_ESCAPE,
+    VK_MENU,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GA_ROOT, GetAncestor, GetForegroundWindow, GetSystemMetrics, SM_CXVIRTUALSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, WHEEL_DELTA, WindowFromPoint,
+};
+
+// Sent with X button events to tell them apart
+const XBUTTON1: u32 = 1;
+const XBUTTON2: u32 = 2;
+
+/// Injects through `SendInput`. Rects and positions are physical pixels,
+/// which holds as the app is per monitor DPI aware.
+pub struct SendInputInjector;
+
+impl InputInjector for SendInputInjector {
+    fn source_rect(&self, source: &CaptureSourceType) -> Result<ScreenRect> {
+        let rect = unsafe {
+            match *source {
+                CaptureSourceType::Monitor(hmonitor) => {
+                    let mut info = MONITORINFO {
+                        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+                        ..Default::default()
+                    };
+                    if !GetMonitorInfoW(HMONITOR(hmonitor as *mut _), &mut info).as_bool() {
+                        return Err(CaptureError::SourceNotFound(source.to_id()));
+                    }
+                    info.rcMonitor
+                }
+                // Without the invisible resize borders, as captured
+                CaptureSourceType::Window(hwnd) => {
+                    let mut rect = RECT::default();
+                    DwmGetWindowAttribute(
+                        HWND(hwnd as *mut _),
+                        DWMWA_EXTENDED_FRAME_BOUNDS,
+                        &mut rect as *mut RECT as *mut _,
+                        std::mem::size_of::<RECT>() as u32,
+                    )
+                    .map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+                    rect
+                }
+            }
+        };
+
+        Ok(ScreenRect {
+            left: rect.left,
+            top: rect.top,
+            width: rect.right - rect.left,
+            height: rect.bottom - rect.top,
+        })
+    }
+
+    fn reaches_source(&self, source: &CaptureSourceType, at: Option<(i32, i32)>) -> bool {
+        let CaptureSourceType::Window(hwnd) = *source else {
+            return true;
+        };
+
+        unsafe {
+            let target = match at {
+                Some((x, y)) => GetAncestor(WindowFromPoint(POINT { x, y }), GA_ROOT),
+                None => GetForegroundWindow(),
+            };
+            target.0 as isize == hwnd
+        }
+    }
+
+    fn inject(&mut self, input: &ScreenInput) -> Result<()> {
+        let inputs = match input {
+            ScreenInput::MoveTo { x, y } => {
+                let (dx, dy) = absolute(*x, *y);
+                vec![mouse(
+                    dx,
+                    dy,
+                    0,
+                    MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+                )]
+            }
+            ScreenInput::Button { button, down } => {
+                let (flags, data) = match (button, down) {
+                    (MouseButton::Left, true) => (MOUSEEVENTF_LEFTDOWN, 0),
+                    (MouseButton::Left, false) => (MOUSEEVENTF_LEFTUP, 0),
+                    (MouseButton::Right, true) => (MOUSEEVENTF_RIGHTDOWN, 0),
+                    (MouseButton::Right, false) => (MOUSEEVENTF_RIGHTUP, 0),
+                    (MouseButton::Middle, true) => (MOUSEEVENTF_MIDDLEDOWN, 0),
+                    (MouseButton::Middle, false) => (MOUSEEVENTF_MIDDLEUP, 0),
+                    (MouseButton::Back, true) => (MOUSEEVENTF_XDOWN, XBUTTON1),
+                    (MouseButton::Back, false) => (MOUSEEVENTF_XUP, XBUTTON1),
+                    (MouseButton::Forward, true) => (MOUSEEVENTF_XDOWN, XBUTTON2),
+                    (MouseButton::Forward, false) => (MOUSEEVENTF_XUP, XBUTTON2),
+                };
+                vec![mouse(0, 0, data, flags)]
+            }
+            // Windows scrolls up and left on positive deltas
+            ScreenInput::Wheel { delta_x, delta_y } => {
+                let notch = WHEEL_DELTA as f32;
+                let mut inputs = Vec::new();
+                if *delta_y != 0.0 {
+                    let data = (-delta_y * notch) as i32;
+                    inputs.push(mouse(0, 0, data as u32, MOUSEEVENTF_WHEEL));
+                }
+                if *delta_x != 0.0 {
+                    let data = (delta_x * notch) as i32;
+                    inputs.push(mouse(0, 0, data as u32, MOUSEEVENTF_HWHEEL));
+                }
+                inputs
+            }
+            ScreenInput::Key { code, down } => {
+                let (vk, extended) = virtual_key(code).ok_or_else(|| {
+                    CaptureError::InvalidConfig(format!("Unknown key code {}", code))
+                })?;
+
+                let mut flags = KEYBD_EVENT_FLAGS(0);
+                if extended {
+                    flags |= KEYEVENTF_EXTENDEDKEY;
+                }
+                if !down {
+                    flags |= KEYEVENTF_KEYUP;
+                }
+                vec![INPUT {
+                    r#type: INPUT_KEYBOARD,
+                    Anonymous: INPUT_0 {
+                        ki: KEYBDINPUT {
+                            wVk: VIRTUAL_KEY(vk),
+                            wScan: unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as u16,
+                            dwFlags: flags,
+                            time: 0,
+                            dwExtraInfo: 0,
+                        },
+                    },
+                }]
+            }
+        };
+
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            // Input into elevated windows is blocked
+            return Err(CaptureError::PlatformError(format!(
+                "SendInput sent {} of {} inputs",
+                sent,
+                inputs.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn revoke_hotkey_down(&self) -> bool {
+        // Ctrl+Alt+Escape, see REVOKE_HOTKEY
+        [VK_CONTROL, VK_MENU, VK_ESCAPE]
+            .iter()
+            .all(|vk| unsafe { GetAsyncKeyState(vk.0 as i32) } as u16 & 0x8000 != 0)
+    }
+}
+
+fn mouse(dx: i32, dy: i32, data: u32, flags: MOUSE_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: data,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+// Absolute mouse positions are 0 to 65535 across the virtual desktop
+fn absolute(x: i32, y: i32) -> (i32, i32) {
+    let (left, top, width, height) = unsafe {
+        (
+            GetSystemMetrics(SM_XVIRTUALSCREEN),
+            GetSystemMetrics(SM_YVIRTUALSCREEN),
+            GetSystemMetrics(SM_CXVIRTUALSCREEN).max(2),
+            GetSystemMetrics(SM_CYVIRTUALSCREEN).max(2),
+        )
+    };
+
+    let scale = |value: i32, origin: i32, size: i32| {
+        ((value - origin) as i64 * 65535 / (size - 1) as i64) as i32
+    };
+    (scale(x, left, width), scale(y, top, height))
+}
+
+// Virtual key for a web `KeyboardEvent.code`, and whether it is an
+// extended key
+fn virtual_key(code: &str) -> Option<(u16, bool)> {
+    if let Some(letter) = code.strip_prefix("Key")
+        && let [c @ b'A'..=b'Z'] = letter.as_bytes()
+    {
+        return Some((*c as u16, false));
+    }
+    if let Some(digit) = code.strip_prefix("Digit")
+        && let [c @ b'0'..=b'9'] = digit.as_bytes()
+    {
+        return Some((*c as u16, false));
+    }
+    if let Some(digit) = code.strip_prefix("Numpad")
+        && let [c @ b'0'..=b'9'] = digit.as_bytes()
+    {
+        return Some((0x60 + (c - b'0') as u16, false));
+    }
+    if let Some(n) = code.strip_prefix('F').and_then(|n| n.parse::<u16>().ok())
+        && (1..=24).contains(&n)
+    {
+        return Some((0x70 + n - 1, false));
+    }
+
+    let key = match code {
+        "Backspace" => (0x08, false),
+        "Tab" => (0x09, false),
+        "Enter" => (0x0D, false),
+        "NumpadEnter" => (0x0D, true),
+        "Pause" => (0x13, false),
+        "CapsLock" => (0x14, false),
+        "Escape" => (0x1B, false),
+        "Space" => (0x20, false),
+        "PageUp" => (0x21, true),
+        "PageDown" => (0x22, true),
+        "End" => (0x23, true),
+        "Home" => (0x24, true),
+        "ArrowLeft" => (0x25, true),
+        "ArrowUp" => (0x26, true),
+        "ArrowRight" => (0x27, true),
+        "ArrowDown" => (0x28, true),
+        "PrintScreen" => (0x2C, true),
+        "Insert" => (0x2D, true),
+        "Delete" => (0x2E, true),
+        "MetaLeft" => (0x5B, true),
+        "MetaRight" => (0x5C, true),
+        "ContextMenu" => (0x5D, true),
+        "NumpadMultiply" => (0x6A, false),
+        "NumpadAdd" => (0x6B, false),
+        "NumpadSubtract" => (0x6D, false),
+        "NumpadDecimal" => (0x6E, false),
+        "NumpadDivide" => (0x6F, true),
+        "NumLock" => (0x90, true),
+        "ScrollLock" => (0x91, false),
+        "ShiftLeft" => (0xA0, false),
+        "ShiftRight" => (0xA1, false),
+        "ControlLeft" => (0xA2, false),
+        "ControlRight" => (0xA3, true),
+        "AltLeft" => (0xA4, false),
+        "AltRight" => (0xA5, true),
+        "Semicolon" => (0xBA, false),
+        "Equal" => (0xBB, false),
+        "Comma" => (0xBC, false),
+        "Minus" => (0xBD, false),
+        "Period" => (0xBE, false),
+        "Slash" => (0xBF, false),
+        "Backquote" => (0xC0, false),
+        "BracketLeft" => (0xDB, false),
+        "Backslash" => (0xDC, false),
+        "BracketRight" => (0xDD, false),
+        "Quote" => (0xDE, false),
+        "IntlBackslash" => (0xE2, false),
+        _ => return None,
+    };
+
+    Some(key)
+}
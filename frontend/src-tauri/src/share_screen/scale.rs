@@ -0,0 +1,251 @@
+// src/share_screen/scale.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::frame::{PixelFormat, VideoFrame};
+use fast_image_resize::images::{Image, ImageRef};
+use fast_image_resize::{FilterType, PixelType, ResizeAlg, ResizeOptions, Resizer};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+    Lanczos3,
+}
+
+impl ScaleFilter {
+    fn to_alg(self) -> ResizeAlg {
+        match self {
+            ScaleFilter::Nearest => ResizeAlg::Nearest,
+            ScaleFilter::Bilinear => ResizeAlg::Convolution(FilterType::Bilinear),
+            ScaleFilter::Lanczos3 => ResizeAlg::Convolution(FilterType::Lanczos3),
+        }
+    }
+}
+
+/// CPU scaler for BGRA and I420 frames, used when no GPU video processor is
+/// available and on platforms without a D3D11 path.
+pub struct FrameScaler {
+    resizer: Resizer,
+    options: ResizeOptions,
+}
+
+impl FrameScaler {
+    pub fn new(filter: ScaleFilter) -> Self {
+        Self {
+            resizer: Resizer::new(),
+            // Captured frames are opaque, premultiplying alpha is wasted work
+            options: ResizeOptions::new()
+                .resize_alg(filter.to_alg())
+                .use_alpha(false),
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: ScaleFilter) {
+        self.options = self.options.clone().resize_alg(filter.to_alg());
+    }
+
+    /// Scales any supported frame, keeping its format and timing.
+    pub fn scale_frame(
+        &mut self,
+        frame: &VideoFrame,
+        dst_width: u32,
+        dst_height: u32,
+    ) -> Result<VideoFrame> {
+        let scaled = match frame.format {
+            PixelFormat::Bgra => {
+                let data = self.scale_bgra(
+                    &frame.data,
+                    frame.width,
+                    frame.height,
+                    frame.strides[0],
+                    dst_width,
+                    dst_height,
+                )?;
+                VideoFrame::bgra(dst_width, dst_height, dst_width as usize * 4, data)?
+            }
+            PixelFormat::I420 => {
+                let mut data = Vec::new();
+                for plane in 0..3 {
+                    let (src_w, src_h) = frame.format.plane_size(plane, frame.width, frame.height);
+                    let (dst_w, dst_h) = frame.format.plane_size(plane, dst_width, dst_height);
+                    data.extend_from_slice(&self.scale_plane(
+                        frame.plane(plane),
+                        src_w,
+                        src_h,
+                        frame.strides[plane],
+                        dst_w,
+                        dst_h,
+                        PixelType::U8,
+                    )?);
+                }
+                VideoFrame::i420(dst_width, dst_height, data)?
+            }
+        };
+
+        Ok(VideoFrame {
+            color_space: frame.color_space,
+            ..scaled.with_timing(frame.timestamp, frame.frame_number)
+        })
+    }
+
+    /// Scales a BGRA frame whose rows are `src_stride` bytes apart into a
+    /// tightly packed `dst_width * dst_height * 4` buffer.
+    pub fn scale_bgra(
+        &mut self,
+        src: &[u8],
+        src_width: u32,
+        src_height: u32,
+        src_stride: usize,
+        dst_width: u32,
+        dst_height: u32,
+    ) -> Result<Vec<u8>> {
+        self.scale_plane(
+            src,
+            src_width,
+            src_height,
+            src_stride,
+            dst_width,
+            dst_height,
+            PixelType::U8x4,
+        )
+    }
+
+    /// Scales a tightly packed I420 frame plane by plane. Odd sizes round the
+    /// chroma planes up, matching `(w + 1) / 2` x `(h + 1) / 2`.
+    pub fn scale_i420(
+        &mut self,
+        src: &[u8],
+        src_width: u32,
+        src_height: u32,
+        dst_width: u32,
+        dst_height: u32,
+    ) -> Result<Vec<u8>> {
+        let src_y = (src_width * src_height) as usize;
+        let (src_cw, src_ch) = (src_width.div_ceil(2), src_height.div_ceil(2));
+        let src_c = (src_cw * src_ch) as usize;
+
+        if src.len() < src_y + 2 * src_c {
+            return Err(CaptureError::ProcessingError(format!(
+                "I420 buffer too small: {} < {}",
+                src.len(),
+                src_y + 2 * src_c
+            )));
+        }
+
+        let (dst_cw, dst_ch) = (dst_width.div_ceil(2), dst_height.div_ceil(2));
+
+        let mut out = self.scale_plane(
+            &src[..src_y],
+            src_width,
+            src_height,
+            src_width as usize,
+            dst_width,
+            dst_height,
+            PixelType::U8,
+        )?;
+
+        for plane in [
+            &src[src_y..src_y + src_c],
+            &src[src_y + src_c..src_y + 2 * src_c],
+        ] {
+            let scaled = self.scale_plane(
+                plane,
+                src_cw,
+                src_ch,
+                src_cw as usize,
+                dst_cw,
+                dst_ch,
+                PixelType::U8,
+            )?;
+            out.extend_from_slice(&scaled);
+        }
+
+        Ok(out)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn scale_plane(
+        &mut self,
+        src: &[u8],
+        src_width: u32,
+        src_height: u32,
+        src_stride: usize,
+        dst_width: u32,
+        dst_height: u32,
+        pixel_type: PixelType,
+    ) -> Result<Vec<u8>> {
+        if src_width == 0 || src_height == 0 || dst_width == 0 || dst_height == 0 {
+            return Err(CaptureError::InvalidConfig(format!(
+                "Cannot scale {}x{} to {}x{}",
+                src_width, src_height, dst_width, dst_height
+            )));
+        }
+
+        let row_bytes = src_width as usize * pixel_type.size();
+        let packed = pack_rows(src, row_bytes, src_stride, src_height as usize)?;
+
+        let src_image = ImageRef::new(src_width, src_height, &packed, pixel_type)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+
+        let mut dst_image = Image::new(dst_width, dst_height, pixel_type);
+
+        self.resizer
+            .resize(&src_image, &mut dst_image, &self.options)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+
+        Ok(dst_image.into_vec())
+    }
+}
+
+/// Drops row padding so the buffer can be handed to `fast_image_resize`,
+/// which expects tightly packed rows. Borrows when there is no padding.
+pub fn pack_rows(
+    src: &[u8],
+    row_bytes: usize,
+    stride: usize,
+    rows: usize,
+) -> Result<Cow<'_, [u8]>> {
+    if stride < row_bytes || src.len() < stride * rows.saturating_sub(1) + row_bytes {
+        return Err(CaptureError::ProcessingError(format!(
+            "Buffer of {} bytes does not hold {} rows of {} bytes with stride {}",
+            src.len(),
+            rows,
+            row_bytes,
+            stride
+        )));
+    }
+
+    if stride == row_bytes {
+        return Ok(Cow::Borrowed(&src[..row_bytes * rows]));
+    }
+
+    let mut packed = Vec::with_capacity(row_bytes * rows);
+    for row in src.chunks(stride).take(rows) {
+        packed.extend_from_slice(&row[..row_bytes]);
+    }
+
+    Ok(Cow::Owned(packed))
+}
+
+/// Largest size with the aspect ratio of `width`x`height` that fits in
+/// `max_width`x`max_height`, with even dimensions. Never upscales.
+pub fn fit_size(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let (width, height) = (width.max(1) as u64, height.max(1) as u64);
+    let (max_width, max_height) = (max_width.max(2) as u64, max_height.max(2) as u64);
+
+    let (fit_width, fit_height) = if width <= max_width && height <= max_height {
+        (width, height)
+    } else if width * max_height > height * max_width {
+        (max_width, height * max_width / width)
+    } else {
+        (width * max_height / height, max_height)
+    };
+
+    (
+        ((fit_width as u32) & !1).max(2),
+        ((fit_height as u32) & !1).max(2),
+    )
+}
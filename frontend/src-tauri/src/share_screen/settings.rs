@@ -0,0 +1,113 @@
+// src/share_screen/settings.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const SETTINGS_FILE: &str = "webrtc.json";
+
+/// STUN or TURN server. Credentials are only used by TURN.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IceServer {
+    /// `stun:host:port`, `turn:host:port?transport=udp` or `turns:...`.
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IceTransportPolicy {
+    #[default]
+    All,
+    /// Only TURN candidates, hides the local addresses from the viewer.
+    Relay,
+}
+
+/// Connectivity settings for preview peer connections, kept in
+/// `webrtc.json` in the app config dir. Without ICE servers previews only
+/// connect on the local network.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebRTCSettings {
+    pub ice_servers: Vec<IceServer>,
+    pub ice_transport_policy: IceTransportPolicy,
+    /// Inclusive local UDP port range for ICE, any port when unset.
+    pub udp_port_range: Option<(u16, u16)>,
+}
+
+impl WebRTCSettings {
+    pub fn validate(&self) -> Result<()> {
+        if let Some((min, max)) = self.udp_port_range
+            && (min == 0 || min > max)
+        {
+            return Err(CaptureError::InvalidConfig(format!(
+                "Invalid UDP port range {}-{}",
+                min, max
+            )));
+        }
+
+        if let Some(server) = self.ice_servers.iter().find(|s| s.urls.is_empty()) {
+            return Err(CaptureError::InvalidConfig(format!(
+                "ICE server without urls: {:?}",
+                server
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the settings, or the defaults when the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(CaptureError::PlatformError(e.to_string())),
+        };
+
+        let settings: Self = serde_json::from_str(&text)
+            .map_err(|e| CaptureError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| CaptureError::PlatformError(e.to_string()))?;
+        }
+
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+        std::fs::write(path, text).map_err(|e| CaptureError::PlatformError(e.to_string()))
+    }
+}
+
+/// Loads the saved settings into the WebRTC server. Called from the app setup.
+pub fn setup(app: &AppHandle) {
+    let settings = match settings_path(app).and_then(|path| WebRTCSettings::load(&path)) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("[Settings] Using default WebRTC settings: {:?}", e);
+            return;
+        }
+    };
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::share_screen::WEBRTC_SERVER
+            .write()
+            .await
+            .configure(settings)
+        {
+            eprintln!("[Settings] Invalid WebRTC settings: {:?}", e);
+        }
+    });
+}
+
+pub fn settings_path(app: &AppHandle) -> Result<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| CaptureError::PlatformError(e.to_string()))
+}
@@ -0,0 +1,341 @@
+// src/share_screen/simulcast.rs
+use crate::share_screen::convert::bgra_to_i420;
+use crate::share_screen::dto::{CaptureConfig, CaptureError, Result};
+use crate::share_screen::encoder::rate::EncoderControl;
+use crate::share_screen::encoder::{
+    DEFAULT_BITRATE_KBPS, EncoderSettings, VideoEncoder, create_encoder,
+};
+use crate::share_screen::frame::{ColorSpace, EncodedFrame, PixelFormat, VideoFrame};
+use crate::share_screen::queue::{Closed, QueueSender, SendOutcome};
+use crate::share_screen::scale::{FrameScaler, ScaleFilter, fit_size};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulcastLayer {
+    pub rid: &'static str,
+    /// Height cap in pixels, 0 keeps the capture size.
+    pub max_height: u32,
+    pub bitrate_kbps: u32,
+}
+
+pub const FULL_LAYER: SimulcastLayer = SimulcastLayer {
+    rid: "f",
+    max_height: 0,
+    bitrate_kbps: DEFAULT_BITRATE_KBPS,
+};
+
+/// Layers published when simulcast is on, highest quality first.
+pub const SIMULCAST_LAYERS: [SimulcastLayer; 3] = [
+    FULL_LAYER,
+    SimulcastLayer {
+        rid: "h",
+        max_height: 720,
+        bitrate_kbps: 600,
+    },
+    SimulcastLayer {
+        rid: "q",
+        max_height: THUMBNAIL_HEIGHT,
+        bitrate_kbps: 150,
+    },
+];
+
+impl SimulcastLayer {
+    /// Layer size for a capture of `width`x`height`, keeping the aspect ratio
+    /// and even dimensions. Layers never upscale.
+    pub fn size_for(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.max_height == 0 || height <= self.max_height {
+            return (width, height);
+        }
+
+        let scaled_width = (width as u64 * self.max_height as u64 / height as u64) as u32;
+        ((scaled_width & !1).max(2), self.max_height & !1)
+    }
+}
+
+/// Layers for the stream, skipping those that would not be smaller than the
+/// layer above them at the configured size.
+pub fn layers_for(config: &CaptureConfig) -> Vec<SimulcastLayer> {
+    if !config.simulcast {
+        return vec![FULL_LAYER];
+    }
+
+    let (width, height) = (config.width.max(0) as u32, config.height.max(0) as u32);
+    let mut layers: Vec<SimulcastLayer> = Vec::new();
+    for layer in SIMULCAST_LAYERS {
+        let above = layers.last().map(|above| above.size_for(width, height).1);
+        if above.is_none_or(|above| layer.size_for(width, height).1 < above) {
+            layers.push(layer);
+        }
+    }
+    layers
+}
+
+/// Height of the live thumbnail, that of the smallest layer.
+pub const THUMBNAIL_HEIGHT: u32 = 180;
+
+// The picker only needs to look live
+const THUMBNAIL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Latest captured frame at thumbnail size, BGRA.
+pub type ThumbnailSlot = Mutex<Option<VideoFrame>>;
+
+/// Keeps the live picker thumbnail of a capture. With simulcast it is fed
+/// the smallest layer's frames, which are already at thumbnail size, and
+/// otherwise scales captured frames before any filter converts them.
+pub struct ThumbnailUpdater {
+    slot: Arc<ThumbnailSlot>,
+    scaler: FrameScaler,
+    last_update: Option<Instant>,
+}
+
+impl ThumbnailUpdater {
+    pub fn new(slot: Arc<ThumbnailSlot>) -> Self {
+        Self {
+            slot,
+            scaler: FrameScaler::new(ScaleFilter::Bilinear),
+            last_update: None,
+        }
+    }
+
+    pub fn update(&mut self, frame: &VideoFrame, now: Instant) -> Result<()> {
+        if self
+            .last_update
+            .is_some_and(|last| now.duration_since(last) < THUMBNAIL_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_update = Some(now);
+
+        let (width, height) = fit_size(frame.width, frame.height, u32::MAX, THUMBNAIL_HEIGHT);
+        let thumbnail = if (width, height) == (frame.width, frame.height) {
+            frame.clone()
+        } else {
+            self.scaler.scale_frame(frame, width, height)?
+        };
+        *self.slot.lock().unwrap() = Some(thumbnail);
+        Ok(())
+    }
+}
+
+/// Encodes a thumbnail as PNG for the picker.
+pub fn thumbnail_png(frame: VideoFrame) -> Result<Vec<u8>> {
+    if frame.format != PixelFormat::Bgra {
+        return Err(CaptureError::ProcessingError(format!(
+            "Expected a BGRA thumbnail, got {:?}",
+            frame.format
+        )));
+    }
+
+    let frame = frame.into_packed();
+    let mut rgba = frame.data;
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .map_err(|e| CaptureError::ProcessingError(e.to_string()))?;
+
+    Ok(png_data)
+}
+
+/// Running totals of one layer's encoder, read by the stats reporter.
+#[derive(Debug, Default)]
+pub struct LayerCounters {
+    // Width in the high half, height in the low half
+    size: AtomicU64,
+    frames: AtomicU64,
+    keyframes: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl LayerCounters {
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.size.load(Ordering::Relaxed);
+        ((size >> 32) as u32, size as u32)
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn keyframes(&self) -> u64 {
+        self.keyframes.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    fn set_size(&self, width: u32, height: u32) {
+        self.size
+            .store(((width as u64) << 32) | height as u64, Ordering::Relaxed);
+    }
+
+    fn record(&self, frame: &EncodedFrame) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.bytes
+            .fetch_add(frame.data.len() as u64, Ordering::Relaxed);
+        if frame.keyframe {
+            self.keyframes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayerSink {
+    pub layer: SimulcastLayer,
+    pub video_tx: QueueSender<EncodedFrame>,
+    pub counters: Arc<LayerCounters>,
+}
+
+/// Encoder for one layer. Every layer is fed from the same captured frames.
+pub struct LayerEncoder {
+    sink: LayerSink,
+    encoder: Box<dyn VideoEncoder>,
+    width: u32,
+    height: u32,
+    bitrate_kbps: u32,
+    current: Option<VideoFrame>,
+    thumbnail: Option<ThumbnailUpdater>,
+    keyframe_period: Duration,
+    last_keyframe: Option<Duration>,
+}
+
+impl LayerEncoder {
+    pub fn new(sink: LayerSink, config: &CaptureConfig, control: &EncoderControl) -> Result<Self> {
+        let (width, height) = sink
+            .layer
+            .size_for(config.width as u32, config.height as u32);
+        let bitrate_kbps = control.layer_kbps(sink.layer.bitrate_kbps);
+
+        let settings = EncoderSettings {
+            width,
+            height,
+            bitrate_kbps,
+            ..EncoderSettings::from_config(config)
+        };
+        let encoder = create_encoder(&settings)?;
+        sink.counters.set_size(width, height);
+
+        Ok(Self {
+            sink,
+            encoder,
+            width,
+            height,
+            bitrate_kbps,
+            current: None,
+            thumbnail: None,
+            keyframe_period: settings.keyframe_period(),
+            last_keyframe: None,
+        })
+    }
+
+    /// Makes this layer's frames the live thumbnail.
+    pub fn with_thumbnail(mut self, slot: Arc<ThumbnailSlot>) -> Self {
+        self.thumbnail = Some(ThumbnailUpdater::new(slot));
+        self
+    }
+
+    pub fn sink(&self) -> &LayerSink {
+        &self.sink
+    }
+
+    /// Scales and converts a newly captured frame for this layer. Frames the
+    /// filter chain already converted to I420 are only scaled.
+    pub fn update(
+        &mut self,
+        frame: &VideoFrame,
+        scaler: &mut FrameScaler,
+        color_space: ColorSpace,
+    ) -> Result<()> {
+        let scaled;
+        let frame = if (frame.width, frame.height) == (self.width, self.height) {
+            frame
+        } else {
+            scaled = scaler.scale_frame(frame, self.width, self.height)?;
+            &scaled
+        };
+
+        if let Some(thumbnail) = &mut self.thumbnail
+            && frame.format == PixelFormat::Bgra
+            && let Err(e) = thumbnail.update(frame, Instant::now())
+        {
+            eprintln!("[Encode] Thumbnail error: {:?}", e);
+        }
+
+        self.current = Some(if frame.format == PixelFormat::I420 {
+            frame.clone()
+        } else {
+            bgra_to_i420(frame, color_space)?
+        });
+        Ok(())
+    }
+
+    /// Encodes the current frame, which repeats the previous one if capture
+    /// had nothing new, and queues the output.
+    pub fn encode(
+        &mut self,
+        timestamp: Duration,
+        sequence: u64,
+        keyframe: bool,
+        control: &EncoderControl,
+    ) -> std::result::Result<(), Closed> {
+        let Some(frame) = self.current.as_mut() else {
+            return Ok(());
+        };
+        frame.timestamp = timestamp;
+        frame.frame_number = sequence;
+
+        let target_kbps = control.layer_kbps(self.sink.layer.bitrate_kbps);
+        if target_kbps != self.bitrate_kbps {
+            if let Err(e) = self.encoder.set_bitrate(target_kbps) {
+                eprintln!("[Encode] Failed to set bitrate: {:?}", e);
+            }
+            self.bitrate_kbps = target_kbps;
+        }
+
+        // Counted in media time, an idle stream ticks too slowly for the
+        // encoder's own frame count to bring a late viewer a picture
+        let keyframe_due = self
+            .last_keyframe
+            .is_some_and(|last| timestamp.saturating_sub(last) >= self.keyframe_period);
+        if keyframe || keyframe_due {
+            self.encoder.request_keyframe();
+        }
+
+        match self.encoder.encode(frame) {
+            Ok(packets) => {
+                for encoded in packets {
+                    if encoded.keyframe {
+                        self.last_keyframe = Some(encoded.timestamp);
+                    }
+                    self.sink.counters.record(&encoded);
+                    // A dropped frame leaves the viewer's decoder without a reference
+                    if self.sink.video_tx.send(encoded)? == SendOutcome::Dropped {
+                        control.request_keyframe();
+                    }
+                }
+            }
+            Err(e) => {
+                if sequence.is_multiple_of(30) {
+                    eprintln!(
+                        "[Encode] {:?} encode error on layer {}: {:?}",
+                        self.encoder.codec(),
+                        self.sink.layer.rid,
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+// src/share_screen/static_content.rs
+use crate::share_screen::frame::VideoFrame;
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Frame rate used while nothing on screen changes. Keeps the stream alive
+/// and lets late viewers recover without spending bits on identical frames.
+pub const KEEPALIVE_FPS: u32 = 1;
+
+/// How long content must stay unchanged before the encoder idles.
+pub const IDLE_AFTER: Duration = Duration::from_secs(1);
+
+const TILE_SIZE: usize = 32;
+
+/// Finds unchanged frames by hashing fixed size tiles and comparing them with
+/// the previous frame.
+#[derive(Default)]
+pub struct StaticDetector {
+    width: u32,
+    height: u32,
+    hashes: Vec<u64>,
+    last_change: Option<Instant>,
+}
+
+impl StaticDetector {
+    /// Hashes `frame` and returns how many tiles differ from the previous
+    /// frame. A new size counts every tile as changed.
+    pub fn dirty_tiles(&mut self, frame: &VideoFrame) -> usize {
+        let hashes = tile_hashes(frame);
+
+        let dirty = if frame.width != self.width || frame.height != self.height {
+            hashes.len()
+        } else {
+            hashes
+                .iter()
+                .zip(&self.hashes)
+                .filter(|(new, old)| new != old)
+                .count()
+        };
+
+        self.width = frame.width;
+        self.height = frame.height;
+        self.hashes = hashes;
+
+        if dirty > 0 {
+            self.last_change = Some(Instant::now());
+        }
+
+        dirty
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.last_change
+            .is_some_and(|changed| changed.elapsed() >= IDLE_AFTER)
+    }
+}
+
+/// One hash per tile, in row major order. A tile covers the same area in
+/// every plane, so a change to chroma alone marks it dirty too.
+fn tile_hashes(frame: &VideoFrame) -> Vec<u64> {
+    let tiles_x = (frame.width as usize).div_ceil(TILE_SIZE);
+    let tiles_y = (frame.height as usize).div_ceil(TILE_SIZE);
+    let mut hashes = vec![0u64; tiles_x * tiles_y];
+
+    if tiles_x == 0 {
+        return hashes;
+    }
+
+    // Tile size in samples of each plane, smaller on subsampled ones
+    let planes: Vec<_> = (0..frame.format.plane_count())
+        .map(|plane| {
+            let (width, height) = frame.format.plane_size(plane, frame.width, frame.height);
+            let tile_width = TILE_SIZE / (frame.width as usize).div_ceil(width as usize);
+            let tile_rows = TILE_SIZE / (frame.height as usize).div_ceil(height as usize);
+            (
+                plane,
+                tile_width * frame.format.bytes_per_sample(),
+                tile_rows,
+            )
+        })
+        .collect();
+
+    hashes
+        .par_chunks_mut(tiles_x)
+        .enumerate()
+        .for_each(|(ty, band)| {
+            for &(plane, tile_bytes, tile_rows) in &planes {
+                let rows = frame.plane_rows(plane);
+                for y in ty * tile_rows..((ty + 1) * tile_rows).min(rows) {
+                    let row = frame.row(plane, y);
+                    for (hash, segment) in band.iter_mut().zip(row.chunks(tile_bytes)) {
+                        *hash = hash_bytes(*hash, segment);
+                    }
+                }
+            }
+        });
+
+    hashes
+}
+
+// Every step is a bijection of the running hash, so a single changed word
+// always changes the result. Collisions need several edits that cancel out.
+fn hash_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut words = bytes.chunks_exact(8);
+    for word in &mut words {
+        hash = (hash ^ u64::from_le_bytes(word.try_into().unwrap()))
+            .wrapping_mul(PRIME)
+            .rotate_left(31);
+    }
+    for &byte in words.remainder() {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME).rotate_left(31);
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 64;
+    const LUMA: usize = SIZE * SIZE;
+    const CHROMA: usize = SIZE / 2 * SIZE / 2;
+
+    fn frame(data: Vec<u8>) -> VideoFrame {
+        VideoFrame::i420(SIZE as u32, SIZE as u32, data).unwrap()
+    }
+
+    #[test]
+    fn unchanged_frame_is_static() {
+        let mut detector = StaticDetector::default();
+        let data = vec![128u8; LUMA + 2 * CHROMA];
+
+        assert_eq!(detector.dirty_tiles(&frame(data.clone())), 4);
+        assert_eq!(detector.dirty_tiles(&frame(data)), 0);
+    }
+
+    #[test]
+    fn chroma_change_marks_its_tile() {
+        let mut detector = StaticDetector::default();
+        let data = vec![128u8; LUMA + 2 * CHROMA];
+        detector.dirty_tiles(&frame(data.clone()));
+
+        // U sample (20, 4) sits in the top right tile
+        let mut changed = data.clone();
+        changed[LUMA + 4 * SIZE / 2 + 20] = 0;
+        assert_eq!(detector.dirty_tiles(&frame(changed)), 1);
+
+        // V sample (4, 20) sits in the bottom left tile
+        let mut changed = data;
+        changed[LUMA + CHROMA + 20 * SIZE / 2 + 4] = 255;
+        assert_eq!(detector.dirty_tiles(&frame(changed)), 2);
+    }
+}
@@ -0,0 +1,279 @@
+// src/share_screen/stats.rs
+use crate::share_screen::dto::{CaptureError, Result};
+use crate::share_screen::events::{self, STREAM_STATS};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use webrtc::rtcp::receiver_report::ReceiverReport;
+
+/// How often running streams report their stats to the frontend.
+pub const STATS_INTERVAL: Duration = Duration::from_secs(2);
+
+// RTP clock of every video codec
+const VIDEO_CLOCK_RATE: f64 = 90_000.0;
+// Seconds from the NTP epoch (1900) to the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+lazy_static! {
+    // Last counter value per key, to turn totals into rates. Only the
+    // reporter samples, so each rate covers one reporting interval.
+    static ref SAMPLES: Mutex<HashMap<String, (Instant, u64)>> = Mutex::new(HashMap::new());
+    // Latest report of every running stream
+    static ref REPORTS: Mutex<HashMap<String, StreamStats>> = Mutex::new(HashMap::new());
+}
+
+/// Payload of `get_stream_stats` and `STREAM_STATS`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamStats {
+    pub id: String,
+    pub target_kbps: u32,
+    pub target_fps: u32,
+    pub layers: Vec<LayerStats>,
+    pub viewers: Vec<ViewerStats>,
+}
+
+/// Encoder side of one simulcast layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerStats {
+    pub rid: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub bitrate_kbps: f64,
+    pub keyframes: u64,
+    /// Encoded frames waiting for the track writer.
+    pub queue_depth: usize,
+    pub dropped_frames: u64,
+}
+
+/// Network side of one viewer's peer connection.
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerStats {
+    pub viewer_id: String,
+    pub bitrate_kbps: f64,
+    pub packets_sent: u64,
+    pub nack_count: u64,
+    #[serde(flatten)]
+    pub feedback: ViewerFeedback,
+    /// Unset until the viewer reports over the control channel.
+    pub reported: Option<ViewerReport>,
+}
+
+/// Playback stats only the viewer knows, sent over the control channel.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerReport {
+    pub fps: f64,
+    pub width: u32,
+    pub height: u32,
+    pub frames_decoded: u64,
+    pub frames_dropped: u64,
+    pub jitter_buffer_ms: f64,
+}
+
+/// What a viewer's RTCP says about its link. Updated by the RTCP reader.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ViewerFeedback {
+    /// Unset until a receiver report answers one of our sender reports.
+    pub rtt_ms: Option<f64>,
+    /// Share of packets lost since the previous report, 0 to 1.
+    pub fraction_lost: f64,
+    pub packets_lost: u32,
+    pub jitter_ms: f64,
+    pub pli_count: u64,
+    pub fir_count: u64,
+}
+
+impl ViewerFeedback {
+    pub fn on_receiver_report(&mut self, rr: &ReceiverReport) {
+        // Simulcast has one report per layer, the worst one counts
+        let Some(worst) = rr.reports.iter().max_by_key(|r| r.fraction_lost) else {
+            return;
+        };
+
+        self.fraction_lost = worst.fraction_lost as f64 / 256.0;
+        self.packets_lost = rr.reports.iter().map(|r| r.total_lost).sum();
+        self.jitter_ms = worst.jitter as f64 / VIDEO_CLOCK_RATE * 1000.0;
+
+        // RTT is the time since the sender report it refers to, minus the
+        // time the viewer held on to it, both in 1/65536 s
+        if worst.last_sender_report != 0 {
+            let rtt = compact_ntp_now()
+                .wrapping_sub(worst.last_sender_report)
+                .wrapping_sub(worst.delay);
+            self.rtt_ms = Some(rtt as f64 / 65536.0 * 1000.0);
+        }
+    }
+}
+
+// Middle 32 bits of the current NTP timestamp, as used by reception reports
+fn compact_ntp_now() -> u32 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = now.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+
+    ((seconds << 16) as u32) | (fraction >> 16) as u32
+}
+
+/// Per second rate of a growing total since it was last sampled under
+/// `key`. Zero on the first sample.
+pub fn rate(key: String, total: u64) -> f64 {
+    let now = Instant::now();
+    let previous = SAMPLES.lock().unwrap().insert(key, (now, total));
+
+    match previous {
+        Some((at, last)) if now > at && total >= last => {
+            (total - last) as f64 / now.duration_since(at).as_secs_f64()
+        }
+        _ => 0.0,
+    }
+}
+
+// Drops the samples and report of a stream that ended
+fn forget(id: &str) {
+    let prefix = format!("{}/", id);
+    SAMPLES
+        .lock()
+        .unwrap()
+        .retain(|key, _| !key.starts_with(&prefix));
+    REPORTS.lock().unwrap().remove(id);
+}
+
+/// The stream's latest report. Asking does not sample the rates, which
+/// would skew the reporter's.
+pub fn latest(id: &str) -> Result<StreamStats> {
+    REPORTS
+        .lock()
+        .unwrap()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))
+}
+
+// Pipeline counters from the capture manager combined with the peer
+// connection stats of every viewer
+async fn collect(id: &str) -> Result<StreamStats> {
+    // One lock at a time, stopping a preview takes both
+    let mut stats = crate::share_screen::MANAGER.read().await.stream_stats(id)?;
+    stats.viewers = crate::share_screen::WEBRTC_SERVER
+        .read()
+        .await
+        .viewer_stats(id)
+        .await?;
+
+    Ok(stats)
+}
+
+/// Emits the stream's stats every `STATS_INTERVAL` until it is stopped.
+/// The first report, with zero rates, is taken right away.
+pub fn spawn_reporter(id: String) {
+    tokio::spawn(async move {
+        loop {
+            match collect(&id).await {
+                Ok(stats) => {
+                    REPORTS.lock().unwrap().insert(id.clone(), stats.clone());
+                    events::emit(STREAM_STATS, stats);
+                }
+                Err(CaptureError::SourceNotFound(_)) => break,
+                Err(e) => eprintln!("[Stats] Failed to collect stats for {}: {:?}", id, e),
+            }
+
+            tokio::time::sleep(STATS_INTERVAL).await;
+        }
+
+        forget(&id);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webrtc::rtcp::reception_report::ReceptionReport;
+
+    fn report(fraction_lost: u8, total_lost: u32, jitter: u32) -> ReceptionReport {
+        ReceptionReport {
+            fraction_lost,
+            total_lost,
+            jitter,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rate_counts_per_second() {
+        let key = "test/rate".to_string();
+        assert_eq!(rate(key.clone(), 1_000), 0.0);
+
+        std::thread::sleep(Duration::from_millis(100));
+        // 1000 more over at least 100 ms, so at most 10000 per second
+        let per_second = rate(key, 2_000);
+        assert!(per_second > 0.0 && per_second <= 10_000.0, "{}", per_second);
+    }
+
+    #[test]
+    fn rate_is_zero_when_total_goes_back() {
+        let key = "test/reset".to_string();
+        rate(key.clone(), 5_000);
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(rate(key.clone(), 10), 0.0);
+        // The lower total is the new baseline
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(rate(key, 20) > 0.0);
+    }
+
+    #[test]
+    fn receiver_report_uses_worst_layer() {
+        let mut low = report(64, 3, 900);
+        // Sent 2 s ago and held for 0.5 s by the viewer
+        low.last_sender_report = compact_ntp_now().wrapping_sub(2 << 16);
+        low.delay = 1 << 15;
+        let high = report(16, 4, 9_000);
+
+        let mut feedback = ViewerFeedback::default();
+        feedback.on_receiver_report(&ReceiverReport {
+            reports: vec![high, low],
+            ..Default::default()
+        });
+
+        assert_eq!(feedback.fraction_lost, 0.25);
+        assert_eq!(feedback.packets_lost, 7);
+        assert_eq!(feedback.jitter_ms, 10.0);
+        let rtt = feedback.rtt_ms.unwrap();
+        assert!((1_500.0..1_550.0).contains(&rtt), "{}", rtt);
+    }
+
+    #[test]
+    fn receiver_report_without_sender_report_has_no_rtt() {
+        let mut feedback = ViewerFeedback::default();
+        feedback.on_receiver_report(&ReceiverReport {
+            reports: vec![report(0, 0, 0)],
+            ..Default::default()
+        });
+        assert_eq!(feedback.rtt_ms, None);
+
+        // An empty report changes nothing
+        feedback.fraction_lost = 0.5;
+        feedback.on_receiver_report(&ReceiverReport::default());
+        assert_eq!(feedback.fraction_lost, 0.5);
+    }
+
+    #[test]
+    fn compact_ntp_is_middle_bits_of_ntp_time() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let compact = compact_ntp_now();
+
+        // Low 16 bits of the NTP seconds, then the top 16 bits of the fraction
+        let seconds = (before.as_secs() + NTP_UNIX_OFFSET) as u32 & 0xffff;
+        assert!(compact >> 16 == seconds || compact >> 16 == (seconds + 1) & 0xffff);
+        let fraction = (compact & 0xffff) as f64 / 65536.0;
+        let expected = before.subsec_nanos() as f64 / 1e9;
+        // Truncation can put it just below, the clock just past a second
+        let apart = (fraction - expected).rem_euclid(1.0);
+        assert!(apart.min(1.0 - apart) < 0.1, "{} vs {}", fraction, expected);
+    }
+}
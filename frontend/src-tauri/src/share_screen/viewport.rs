@@ -0,0 +1,350 @@
+// src/share_screen/viewport.rs
+use crate::share_screen::dto::Result;
+use crate::share_screen::filter::{FrameFilter, crop};
+use crate::share_screen::frame::VideoFrame;
+use crate::share_screen::scale::{FrameScaler, ScaleFilter};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FollowTarget {
+    #[default]
+    Cursor,
+    FocusedWindow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoomConfig {
+    /// Magnification, 2.0 shows a quarter of the source.
+    pub zoom: f32,
+    pub follow: FollowTarget,
+    /// Time the viewport takes to catch up with a new target.
+    pub smoothing_secs: f32,
+    /// Central part of the viewport, as a fraction of its size, where the
+    /// target can move without panning.
+    pub dead_zone: f32,
+}
+
+impl Default for ZoomConfig {
+    fn default() -> Self {
+        Self {
+            zoom: 2.0,
+            follow: FollowTarget::default(),
+            smoothing_secs: 0.3,
+            dead_zone: 0.4,
+        }
+    }
+}
+
+/// Visible part of the source, in source pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Zoomed window onto the source that pans smoothly after a target point.
+/// Pure state, driven by `update` with target positions and elapsed time.
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    config: ZoomConfig,
+    source_width: f32,
+    source_height: f32,
+    center: Option<(f32, f32)>,
+    goal: Option<(f32, f32)>,
+}
+
+impl Viewport {
+    pub fn new(config: ZoomConfig, source_width: u32, source_height: u32) -> Self {
+        Self {
+            config,
+            source_width: source_width.max(1) as f32,
+            source_height: source_height.max(1) as f32,
+            center: None,
+            goal: None,
+        }
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        let zoom = self.config.zoom.max(1.0);
+        (self.source_width / zoom, self.source_height / zoom)
+    }
+
+    /// Moves the viewport towards `target` for `elapsed` and returns the new
+    /// visible rect. Without a target the viewport keeps easing to the last
+    /// one. The first target is jumped to.
+    pub fn update(&mut self, target: Option<(f32, f32)>, elapsed: Duration) -> ViewRect {
+        let (width, height) = self.size();
+
+        if let Some((tx, ty)) = target {
+            let (gx, gy) = self.goal.unwrap_or((tx, ty));
+            let (free_x, free_y) = (
+                width * self.config.dead_zone.clamp(0.0, 1.0) / 2.0,
+                height * self.config.dead_zone.clamp(0.0, 1.0) / 2.0,
+            );
+
+            // Pan only as far as needed to bring the target back inside the
+            // dead zone
+            self.goal = Some((
+                gx + (tx - gx).signum() * ((tx - gx).abs() - free_x).max(0.0),
+                gy + (ty - gy).signum() * ((ty - gy).abs() - free_y).max(0.0),
+            ));
+        }
+
+        let goal = self.goal.map(|goal| self.clamp(goal));
+        let center = match (self.center, goal) {
+            (None, Some(goal)) => goal,
+            (Some((cx, cy)), Some((gx, gy))) => {
+                // Exponential ease, about 95% of the way after smoothing_secs
+                let tau = (self.config.smoothing_secs / 3.0).max(f32::EPSILON);
+                let step = 1.0 - (-elapsed.as_secs_f32() / tau).exp();
+                (cx + (gx - cx) * step, cy + (gy - cy) * step)
+            }
+            (center, None) => center.unwrap_or((self.source_width / 2.0, self.source_height / 2.0)),
+        };
+        let (cx, cy) = self.clamp(center);
+        self.center = Some((cx, cy));
+
+        ViewRect {
+            x: cx - width / 2.0,
+            y: cy - height / 2.0,
+            width,
+            height,
+        }
+    }
+
+    /// True while the viewport has not reached its goal.
+    pub fn is_moving(&self) -> bool {
+        match (self.center, self.goal) {
+            (Some((cx, cy)), Some(goal)) => {
+                let (gx, gy) = self.clamp(goal);
+                (gx - cx).abs() >= 0.5 || (gy - cy).abs() >= 0.5
+            }
+            _ => false,
+        }
+    }
+
+    // Keeps the whole viewport on the source
+    fn clamp(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (width, height) = self.size();
+        (
+            x.clamp(width / 2.0, self.source_width - width / 2.0),
+            y.clamp(height / 2.0, self.source_height - height / 2.0),
+        )
+    }
+}
+
+/// Position to follow in source pixels, `None` when it is off the source.
+pub type PointerSource = Box<dyn Fn() -> Option<(f32, f32)> + Send>;
+
+/// Crops source resolution frames to the viewport and scales the crop to
+/// the output size, so a zoomed share shows detail the downscaled stream
+/// would have lost.
+pub struct ZoomFilter {
+    viewport: Viewport,
+    pointer: PointerSource,
+    scaler: FrameScaler,
+    output: (u32, u32),
+    last_update: Option<Instant>,
+    // Bumped while panning, so the encoder keeps redrawing a static screen
+    ticks: u64,
+}
+
+impl ZoomFilter {
+    pub fn new(viewport: Viewport, pointer: PointerSource, output: (u32, u32)) -> Self {
+        Self {
+            viewport,
+            pointer,
+            scaler: FrameScaler::new(ScaleFilter::Lanczos3),
+            output,
+            last_update: None,
+            ticks: 0,
+        }
+    }
+}
+
+impl FrameFilter for ZoomFilter {
+    fn name(&self) -> &'static str {
+        "zoom"
+    }
+
+    fn output_size(&self, _width: u32, _height: u32) -> (u32, u32) {
+        self.output
+    }
+
+    // Pointer moves come with a captured frame, since the cursor is part of
+    // the capture
+    fn revision(&self) -> u64 {
+        self.ticks
+    }
+
+    fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame> {
+        let now = Instant::now();
+        let elapsed = self
+            .last_update
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        self.last_update = Some(now);
+
+        let view = self.viewport.update((self.pointer)(), elapsed);
+        if self.viewport.is_moving() {
+            self.ticks = self.ticks.wrapping_add(1);
+        }
+
+        // Frames arrive at source size, unless the source was resized and
+        // the chain not rebuilt yet
+        let scale_x = frame.width as f32 / self.viewport.source_width;
+        let scale_y = frame.height as f32 / self.viewport.source_height;
+        let zoomed = crop(
+            &frame,
+            (view.x * scale_x) as u32,
+            (view.y * scale_y) as u32,
+            (view.width * scale_x).round() as u32,
+            (view.height * scale_y).round() as u32,
+        )?;
+
+        let (width, height) = self.output;
+        self.scaler.scale_frame(&zoomed, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 1920;
+    const HEIGHT: u32 = 1080;
+
+    fn zoomed(zoom: f32, dead_zone: f32) -> Viewport {
+        let config = ZoomConfig {
+            zoom,
+            dead_zone,
+            ..ZoomConfig::default()
+        };
+        Viewport::new(config, WIDTH, HEIGHT)
+    }
+
+    fn origin(view: ViewRect) -> (f32, f32) {
+        (view.x, view.y)
+    }
+
+    fn secs(secs: f32) -> Duration {
+        Duration::from_secs_f32(secs)
+    }
+
+    #[test]
+    fn starts_centered_without_a_target() {
+        let mut viewport = zoomed(2.0, 0.4);
+        let view = viewport.update(None, Duration::ZERO);
+
+        assert_eq!(
+            view,
+            ViewRect {
+                x: 480.0,
+                y: 270.0,
+                width: 960.0,
+                height: 540.0,
+            }
+        );
+        assert!(!viewport.is_moving());
+    }
+
+    #[test]
+    fn jumps_to_the_first_target() {
+        let mut viewport = zoomed(2.0, 0.4);
+        let view = viewport.update(Some((1000.0, 500.0)), Duration::ZERO);
+
+        assert_eq!(origin(view), (520.0, 230.0));
+        assert!(!viewport.is_moving());
+    }
+
+    #[test]
+    fn clamps_at_every_edge() {
+        let corners = [
+            ((0.0, 0.0), (0.0, 0.0)),
+            ((1920.0, 0.0), (960.0, 0.0)),
+            ((0.0, 1080.0), (0.0, 540.0)),
+            ((1920.0, 1080.0), (960.0, 540.0)),
+            ((-500.0, 540.0), (0.0, 270.0)),
+            ((960.0, 5000.0), (480.0, 540.0)),
+        ];
+
+        for (target, expected) in corners {
+            let mut viewport = zoomed(2.0, 0.4);
+            let view = viewport.update(Some(target), Duration::ZERO);
+            assert_eq!(origin(view), expected, "target {:?}", target);
+        }
+
+        // Panning stops at the edge too
+        let mut viewport = zoomed(2.0, 0.0);
+        viewport.update(Some((960.0, 540.0)), Duration::ZERO);
+        let view = viewport.update(Some((1919.0, 1079.0)), secs(10.0));
+        assert_eq!(origin(view), (960.0, 540.0));
+        assert!(!viewport.is_moving());
+    }
+
+    #[test]
+    fn zoom_one_shows_the_whole_source() {
+        let full = ViewRect {
+            x: 0.0,
+            y: 0.0,
+            width: 1920.0,
+            height: 1080.0,
+        };
+
+        // Less than 1 does not zoom out past the source
+        for zoom in [1.0, 0.5] {
+            let mut viewport = zoomed(zoom, 0.4);
+            assert_eq!(viewport.update(Some((100.0, 900.0)), Duration::ZERO), full);
+            assert_eq!(viewport.update(Some((1800.0, 50.0)), secs(1.0)), full);
+        }
+    }
+
+    #[test]
+    fn dead_zone_leaves_the_goal_alone() {
+        // A 960x540 viewport with a 40% dead zone moves freely 192x108
+        // around its center
+        let mut viewport = zoomed(2.0, 0.4);
+        viewport.update(Some((960.0, 540.0)), Duration::ZERO);
+
+        for target in [(1150.0, 540.0), (770.0, 640.0), (1100.0, 440.0)] {
+            let view = viewport.update(Some(target), secs(1.0));
+            assert_eq!(origin(view), (480.0, 270.0), "target {:?}", target);
+            assert!(!viewport.is_moving());
+        }
+
+        // Past it, the goal pans just far enough to bring the target back
+        viewport.update(Some((1252.0, 540.0)), Duration::ZERO);
+        assert!(viewport.is_moving());
+        let view = viewport.update(None, secs(10.0));
+        assert_eq!(origin(view), (580.0, 270.0));
+    }
+
+    #[test]
+    fn eases_most_of_the_way_in_smoothing_secs() {
+        let mut viewport = zoomed(2.0, 0.0);
+        let smoothing = ZoomConfig::default().smoothing_secs;
+        viewport.update(Some((960.0, 540.0)), Duration::ZERO);
+
+        let view = viewport.update(Some((1260.0, 540.0)), secs(smoothing));
+        let progress = (view.x - 480.0) / 300.0;
+        assert!((0.94..0.96).contains(&progress), "progress {}", progress);
+        assert!(viewport.is_moving());
+
+        // Steps add up to the same ease
+        let mut stepped = zoomed(2.0, 0.0);
+        stepped.update(Some((960.0, 540.0)), Duration::ZERO);
+        stepped.update(Some((1260.0, 540.0)), secs(smoothing / 3.0));
+        stepped.update(None, secs(smoothing / 3.0));
+        let stepped_view = stepped.update(None, secs(smoothing / 3.0));
+        assert!((stepped_view.x - view.x).abs() < 0.01);
+
+        // Without a new target it settles on the last one
+        let view = viewport.update(None, secs(10.0));
+        assert_eq!(origin(view), (780.0, 270.0));
+        assert!(!viewport.is_moving());
+    }
+}
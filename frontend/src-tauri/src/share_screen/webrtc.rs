@@ -1,177 +1,471 @@
 // src/share_screen/webrtc.rs
-use crate::share_screen::dto::{CaptureError, PreviewOffer, Result};
+use crate::share_screen::control::{CONTROL_CHANNEL, ControlChannel, ControlMessage};
+use crate::share_screen::dto::{
+    CaptureError, PreviewAnswer, PreviewIceCandidate, PreviewOffer, PreviewState,
+    PreviewStateChanged, Result,
+};
+use crate::share_screen::encoder::VideoCodec;
+use crate::share_screen::encoder::rate::{BitrateEstimator, EncoderControl, MIN_BITRATE_KBPS};
+use crate::share_screen::events::{self, CONNECTION_STATE, ICE_CANDIDATE};
+use crate::share_screen::frame::EncodedFrame;
+use crate::share_screen::pacer::SampleClock;
+use crate::share_screen::queue::QueueReceiver;
+use crate::share_screen::remote::{self, RevokeReason};
+use crate::share_screen::settings::{IceTransportPolicy, WebRTCSettings};
+use crate::share_screen::simulcast::SimulcastLayer;
+use crate::share_screen::stats::{self, ViewerFeedback, ViewerReport, ViewerStats};
 // use std::collections::HashMap;
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tokio::sync::Mutex;
-use webrtc::api::APIBuilder;
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
-use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::api::setting_engine::SettingEngine;
+use webrtc::api::{API, APIBuilder};
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::dtls_transport::dtls_transport_state::RTCDtlsTransportState;
+use webrtc::ice::udp_network::{EphemeralUDP, UDPNetwork};
+use webrtc::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::media::Sample;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
-use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
+use webrtc::rtcp::receiver_report::ReceiverReport;
+use webrtc::rtp_transceiver::RTCRtpTransceiverInit;
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
+use webrtc::stats::StatsReportType;
 use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 // use webrtc::track::track_local::TrackLocal;
 
+// How long a disconnected viewer has to reconnect before it is dropped
+const DISCONNECT_GRACE: Duration = Duration::from_secs(5);
+
+struct Viewer {
+    peer: Arc<RTCPeerConnection>,
+    feedback: Arc<std::sync::Mutex<ViewerFeedback>>,
+    control_channel: Arc<ControlChannel>,
+    /// Asked to pause over the control channel.
+    paused: bool,
+    report: Option<ViewerReport>,
+}
+
 pub struct PreviewConnection {
-    peer_conn: Option<Arc<RTCPeerConnection>>,
-    track: Arc<TrackLocalStaticSample>,
+    /// One peer connection per viewer, all sending the same tracks.
+    viewers: HashMap<String, Viewer>,
+    next_viewer: u64,
+    /// One track per simulcast layer, highest quality first.
+    tracks: Vec<Arc<TrackLocalStaticSample>>,
+    codec: VideoCodec,
+    control: Arc<EncoderControl>,
+    rates: Arc<ViewerRates>,
+}
+
+impl PreviewConnection {
+    // The encoder only rests while every viewer has paused
+    fn update_paused(&self) {
+        self.control
+            .set_paused(!self.viewers.is_empty() && self.viewers.values().all(|v| v.paused));
+    }
+}
+
+/// Bitrate each viewer's feedback allows. The encoder is shared, so it
+/// follows the slowest viewer.
+#[derive(Debug, Default)]
+struct ViewerRates(std::sync::Mutex<HashMap<String, u32>>);
+
+impl ViewerRates {
+    /// Records a viewer's estimate and returns the lowest one.
+    fn set(&self, viewer_id: &str, kbps: u32) -> u32 {
+        let mut rates = self.0.lock().unwrap();
+        rates.insert(viewer_id.to_string(), kbps);
+        rates.values().copied().min().unwrap_or(kbps)
+    }
+
+    /// Lowest estimate of the remaining viewers.
+    fn remove(&self, viewer_id: &str) -> Option<u32> {
+        let mut rates = self.0.lock().unwrap();
+        rates.remove(viewer_id);
+        rates.values().copied().min()
+    }
 }
 
 pub struct WebRTCServer {
     preview_connections: Mutex<HashMap<String, PreviewConnection>>,
-    // api: Arc<API>,
+    settings: WebRTCSettings,
+    // Built on first use and shared by every peer connection
+    api: Option<Arc<API>>,
 }
 
 impl WebRTCServer {
     pub fn new() -> Self {
         Self {
             preview_connections: Mutex::new(HashMap::new()),
+            settings: WebRTCSettings::default(),
+            api: None,
         }
     }
 
-    pub async fn create_preview_track(
-        &mut self,
-        id: &str,
-        mut frame_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
-    ) -> Result<()> {
-        let track = Arc::new(TrackLocalStaticSample::new(
-            RTCRtpCodecCapability {
-                mime_type: "video/VP8".to_owned(),
-                clock_rate: 90000,
-                channels: 0,
-                sdp_fmtp_line: "".to_owned(),
-                rtcp_feedback: vec![],
-            },
-            format!("video-{}", id),
-            format!("preview-{}", id),
-        ));
+    pub fn settings(&self) -> &WebRTCSettings {
+        &self.settings
+    }
 
-        let track_clone = track.clone();
-        let id_clone = id.to_string();
+    /// Applies to peer connections created from now on. Running previews
+    /// keep the settings they were negotiated with.
+    pub fn configure(&mut self, settings: WebRTCSettings) -> Result<()> {
+        settings.validate()?;
+        self.settings = settings;
+        self.api = None;
+        Ok(())
+    }
 
-        tokio::spawn(async move {
-            let mut frame_count = 0u64;
+    fn api(&mut self) -> Result<Arc<API>> {
+        if let Some(api) = &self.api {
+            return Ok(api.clone());
+        }
 
-            while let Some(vp8_data) = frame_rx.recv().await {
-                frame_count += 1;
+        let mut m = MediaEngine::default();
+        m.register_default_codecs()
+            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
 
-                let sample = Sample {
-                    data: vp8_data.into(),
-                    duration: std::time::Duration::from_millis(100),
-                    timestamp: SystemTime::now(),
-                    packet_timestamp: 0,
-                    prev_dropped_packets: 0,
-                    prev_padding_packets: 0,
-                };
+        let mut registry = webrtc::interceptor::registry::Registry::new();
 
-                if let Err(e) = track_clone.write_sample(&sample).await {
-                    eprintln!(
-                        "[WebRTC] Failed to write VP8 sample for {}: {}",
-                        id_clone, e
-                    );
-                }
-            }
-        });
+        registry = register_default_interceptors(registry, &mut m)
+            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+        let mut setting_engine = SettingEngine::default();
+        if let Some((min, max)) = self.settings.udp_port_range {
+            let ports = EphemeralUDP::new(min, max)
+                .map_err(|e| CaptureError::InvalidConfig(e.to_string()))?;
+            setting_engine.set_udp_network(UDPNetwork::Ephemeral(ports));
+        }
+
+        let api = Arc::new(
+            APIBuilder::new()
+                .with_media_engine(m)
+                .with_interceptor_registry(registry)
+                .with_setting_engine(setting_engine)
+                .build(),
+        );
+        self.api = Some(api.clone());
+
+        Ok(api)
+    }
+
+    fn rtc_configuration(&self) -> RTCConfiguration {
+        RTCConfiguration {
+            ice_servers: self
+                .settings
+                .ice_servers
+                .iter()
+                .map(|server| RTCIceServer {
+                    urls: server.urls.clone(),
+                    username: server.username.clone(),
+                    credential: server.credential.clone(),
+                })
+                .collect(),
+            ice_transport_policy: match self.settings.ice_transport_policy {
+                IceTransportPolicy::All => RTCIceTransportPolicy::All,
+                IceTransportPolicy::Relay => RTCIceTransportPolicy::Relay,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub async fn create_preview_track(
+        &mut self,
+        id: &str,
+        codec: VideoCodec,
+        control: Arc<EncoderControl>,
+        layers: Vec<(SimulcastLayer, QueueReceiver<EncodedFrame>)>,
+    ) -> Result<()> {
+        let simulcast = layers.len() > 1;
+        let mut tracks = Vec::new();
+
+        for (layer, frame_rx) in layers {
+            // Simulcast encodings share the track id and differ by RID
+            let track = Arc::new(if simulcast {
+                TrackLocalStaticSample::new_with_rid(
+                    codec.rtp_capability(),
+                    format!("video-{}", id),
+                    layer.rid.to_string(),
+                    format!("preview-{}", id),
+                )
+            } else {
+                TrackLocalStaticSample::new(
+                    codec.rtp_capability(),
+                    format!("video-{}", id),
+                    format!("preview-{}", id),
+                )
+            });
+
+            tokio::spawn(write_samples(
+                format!("{}/{}", id, layer.rid),
+                codec,
+                track.clone(),
+                frame_rx,
+                control.clone(),
+            ));
+            tracks.push(track);
+        }
 
         let mut map = self.preview_connections.lock().await;
-        // Store track (PC will be created on get_preview_offer)
+        // Store track (viewers are added by the offer and answer flows)
         map.insert(
             id.to_string(),
             PreviewConnection {
-                peer_conn: None,
-                track,
+                viewers: HashMap::new(),
+                next_viewer: 0,
+                tracks,
+                codec,
+                control,
+                rates: Arc::default(),
             },
         );
 
         Ok(())
     }
 
+    /// Adds a viewer to the preview and returns the offer for its peer
+    /// connection. Viewers are independent, each answers and closes on its own.
     pub async fn get_preview_offer(&mut self, id: &str) -> Result<PreviewOffer> {
-        let mut m = MediaEngine::default();
-        m.register_default_codecs()
-            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+        let (viewer_id, peer_connection) = self.add_viewer(id, true).await?;
 
-        let mut registry = webrtc::interceptor::registry::Registry::new();
+        let offer = match create_offer(&peer_connection).await {
+            Ok(offer) => offer,
+            Err(e) => {
+                self.close_viewer(id, &viewer_id).await;
+                return Err(e);
+            }
+        };
 
-        registry = register_default_interceptors(registry, &mut m)
+        let preview_offer = PreviewOffer {
+            id: id.to_string(),
+            viewer_id,
+            sdp: offer.sdp,
+        };
+
+        Ok(preview_offer)
+    }
+
+    /// Adds a viewer from an offer made by the viewer, for viewers that can
+    /// only be the offerer. The offer needs a video section the viewer
+    /// receives on. Only the full layer is sent, simulcast needs our offer.
+    /// The control channel is the viewer's own, labelled `CONTROL_CHANNEL`.
+    pub async fn answer_preview_offer(&mut self, id: &str, sdp: String) -> Result<PreviewAnswer> {
+        let offer = RTCSessionDescription::offer(sdp)
             .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
 
-        let api = APIBuilder::new()
-            .with_media_engine(m)
-            .with_interceptor_registry(registry)
-            .build();
+        let (viewer_id, peer_connection) = self.add_viewer(id, false).await?;
 
-        let config = RTCConfiguration {
-            ..Default::default()
+        let answer = match answer_offer(&peer_connection, offer).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                self.close_viewer(id, &viewer_id).await;
+                return Err(e);
+            }
         };
 
+        Ok(PreviewAnswer {
+            id: id.to_string(),
+            viewer_id,
+            sdp: answer.sdp,
+        })
+    }
+
+    // Creates a viewer's peer connection with the preview tracks, the
+    // control channel and its RTCP, ICE and state handlers, and registers it
+    // on the preview. Simulcast and the control channel are ours to offer.
+    async fn add_viewer(
+        &mut self,
+        id: &str,
+        offerer: bool,
+    ) -> Result<(String, Arc<RTCPeerConnection>)> {
+        let api = self.api()?;
+        let config = self.rtc_configuration();
+
         let peer_connection = Arc::new(
             api.new_peer_connection(config)
                 .await
                 .map_err(|e| CaptureError::WebRTCError(e.to_string()))?,
         );
 
-        let track = {
+        // An unregistered peer connection would keep its ICE agent and
+        // sockets open
+        match self.setup_viewer(id, offerer, &peer_connection).await {
+            Ok(viewer_id) => Ok((viewer_id, peer_connection)),
+            Err(e) => {
+                let _ = peer_connection.close().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn setup_viewer(
+        &self,
+        id: &str,
+        offerer: bool,
+        peer_connection: &Arc<RTCPeerConnection>,
+    ) -> Result<String> {
+        let (viewer_id, tracks, codec, control, rates) = {
             let mut map = self.preview_connections.lock().await;
             let conn = map
                 .get_mut(id)
                 .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
 
-            let track = conn.track.clone();
-            track
+            conn.next_viewer += 1;
+            (
+                format!("viewer_{}", conn.next_viewer),
+                conn.tracks.clone(),
+                conn.codec,
+                conn.control.clone(),
+                conn.rates.clone(),
+            )
         };
 
-        peer_connection
-            .add_track(track)
+        let (first, rest) = tracks
+            .split_first()
+            .ok_or_else(|| CaptureError::WebRTCError("No tracks for preview".to_string()))?;
+
+        let transceiver = peer_connection
+            .add_transceiver_from_track(
+                first.clone(),
+                Some(RTCRtpTransceiverInit {
+                    direction: RTCRtpTransceiverDirection::Sendonly,
+                    send_encodings: vec![],
+                }),
+            )
             .await
             .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
 
-        {
-            let mut map = self.preview_connections.lock().await;
-            if let Some(conn) = map.get_mut(id) {
-                conn.peer_conn = Some(peer_connection.clone());
+        // Remaining simulcast layers become extra encodings on the same sender
+        let sender = transceiver.sender().await;
+        if offerer {
+            for track in rest {
+                sender
+                    .add_encoding(track.clone())
+                    .await
+                    .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
             }
         }
 
-        let offer = peer_connection
-            .create_offer(None)
+        // Only offer the codec the encoder produces
+        transceiver
+            .set_codec_preferences(vec![codec.rtp_parameters()])
             .await
             .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
 
-        peer_connection
-            .set_local_description(offer.clone())
-            .await
-            .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+        let feedback = Arc::new(std::sync::Mutex::new(ViewerFeedback::default()));
+        tokio::spawn(read_sender_rtcp(
+            id.to_string(),
+            viewer_id.clone(),
+            sender,
+            control,
+            rates,
+            feedback.clone(),
+        ));
 
-        let preview_offer = PreviewOffer {
-            id: id.to_string(),
-            sdp: offer.sdp,
-        };
+        // Candidates are trickled to the frontend as they are gathered,
+        // the offer goes out before any of them
+        let (candidate_id, candidate_viewer) = (id.to_string(), viewer_id.clone());
+        peer_connection.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+            let (id, viewer_id) = (candidate_id.clone(), candidate_viewer.clone());
+            Box::pin(async move {
+                let init = match candidate.map(|c| c.to_json()).transpose() {
+                    Ok(init) => init,
+                    Err(e) => {
+                        eprintln!("[WebRTC] {} bad local candidate: {}", id, e);
+                        return;
+                    }
+                };
 
-        Ok(preview_offer)
-    }
+                // No candidate marks the end of gathering
+                let (candidate, sdp_mid, sdp_mline_index) = match init {
+                    Some(init) => (Some(init.candidate), init.sdp_mid, init.sdp_mline_index),
+                    None => (None, None, None),
+                };
+                events::emit(
+                    ICE_CANDIDATE,
+                    PreviewIceCandidate {
+                        id,
+                        viewer_id,
+                        candidate,
+                        sdp_mid,
+                        sdp_mline_index,
+                    },
+                );
+            })
+        }));
+
+        // As the answerer the channel is the one the viewer's offer opens
+        let control_channel = Arc::new(ControlChannel::default());
+        if offerer {
+            let channel = peer_connection
+                .create_data_channel(CONTROL_CHANNEL, None)
+                .await
+                .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+            control_channel.attach(id, &viewer_id, channel);
+        } else {
+            let (channel_id, channel_viewer) = (id.to_string(), viewer_id.clone());
+            let control_channel = control_channel.clone();
+            peer_connection.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+                if channel.label() == CONTROL_CHANNEL {
+                    control_channel.attach(&channel_id, &channel_viewer, channel);
+                }
+                Box::pin(async {})
+            }));
+        }
 
-    pub async fn accept_preview_answer(&self, id: &str, sdp: String) -> Result<()> {
-        // Lock the map to get the connection
-        let peer_connection = {
-            let map = self.preview_connections.lock().await;
-            let connection = map
-                .get(id)
+        watch_connection_state(id, &viewer_id, peer_connection);
+
+        {
+            let mut map = self.preview_connections.lock().await;
+            let conn = map
+                .get_mut(id)
                 .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
+            conn.viewers.insert(
+                viewer_id.clone(),
+                Viewer {
+                    peer: peer_connection.clone(),
+                    feedback,
+                    control_channel,
+                    paused: false,
+                    report: None,
+                },
+            );
+            conn.update_paused();
+        }
 
-            // Clone the Arc so we can drop the lock before awaiting
-            connection
-                .peer_conn
-                .as_ref()
-                .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?
-                .clone()
-        }; // lock dropped here
+        Ok(viewer_id)
+    }
+
+    // Cloned out so the lock is not held while awaiting the peer
+    async fn viewer(&self, id: &str, viewer_id: &str) -> Result<Arc<RTCPeerConnection>> {
+        let map = self.preview_connections.lock().await;
+        map.get(id)
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?
+            .viewers
+            .get(viewer_id)
+            .map(|viewer| viewer.peer.clone())
+            .ok_or_else(|| CaptureError::SourceNotFound(format!("{}/{}", id, viewer_id)))
+    }
+
+    pub async fn accept_preview_answer(
+        &self,
+        id: &str,
+        viewer_id: &str,
+        sdp: String,
+    ) -> Result<()> {
+        let peer_connection = self.viewer(id, viewer_id).await?;
 
         let answer = RTCSessionDescription::answer(sdp)
             .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
@@ -184,6 +478,72 @@ impl WebRTCServer {
         Ok(())
     }
 
+    /// Network stats of every viewer of the preview, from the peer
+    /// connection stats and the viewer's RTCP feedback.
+    pub async fn viewer_stats(&self, id: &str) -> Result<Vec<ViewerStats>> {
+        let viewers: Vec<_> = {
+            let map = self.preview_connections.lock().await;
+            map.get(id)
+                .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?
+                .viewers
+                .iter()
+                .map(|(viewer_id, viewer)| {
+                    (
+                        viewer_id.clone(),
+                        viewer.peer.clone(),
+                        *viewer.feedback.lock().unwrap(),
+                        viewer.report,
+                    )
+                })
+                .collect()
+        };
+
+        let mut viewer_stats = Vec::new();
+        for (viewer_id, peer, feedback, reported) in viewers {
+            // Simulcast layers are separate outbound streams
+            let (mut bytes_sent, mut packets_sent, mut nack_count) = (0, 0, 0);
+            for report in peer.get_stats().await.reports.into_values() {
+                if let StatsReportType::OutboundRTP(outbound) = report {
+                    bytes_sent += outbound.bytes_sent;
+                    packets_sent += outbound.packets_sent;
+                    nack_count += outbound.nack_count;
+                }
+            }
+
+            viewer_stats.push(ViewerStats {
+                bitrate_kbps: stats::rate(format!("{}/{}/bytes", id, viewer_id), bytes_sent) * 8.0
+                    / 1000.0,
+                viewer_id,
+                packets_sent,
+                nack_count,
+                feedback,
+                reported,
+            });
+        }
+
+        Ok(viewer_stats)
+    }
+
+    /// Closes one viewer's peer connection. Returns how many viewers the
+    /// preview has left, `None` when the viewer was already gone.
+    pub async fn close_viewer(&self, id: &str, viewer_id: &str) -> Option<usize> {
+        let (pc, remaining) = {
+            let mut map = self.preview_connections.lock().await;
+            let conn = map.get_mut(id)?;
+            let pc = conn.viewers.remove(viewer_id)?.peer;
+
+            if let Some(kbps) = conn.rates.remove(viewer_id) {
+                conn.control.set_target_kbps(kbps);
+            }
+            conn.update_paused();
+            (pc, conn.viewers.len())
+        };
+        remote::end_session(id, Some(viewer_id), RevokeReason::Disconnected);
+
+        let _ = pc.close().await;
+        Some(remaining)
+    }
+
     pub async fn close_preview(&self, id: &str) {
         let connection = {
             let mut map = self.preview_connections.lock().await;
@@ -191,8 +551,8 @@ impl WebRTCServer {
         };
 
         if let Some(connection) = connection {
-            if let Some(pc) = connection.peer_conn {
-                let _ = pc.close().await;
+            for viewer in connection.viewers.into_values() {
+                let _ = viewer.peer.close().await;
             }
         }
     }
@@ -204,28 +564,95 @@ impl WebRTCServer {
         };
 
         for connection in connections {
-            if let Some(pc) = connection.peer_conn {
-                let _ = pc.close().await;
+            for viewer in connection.viewers.into_values() {
+                let _ = viewer.peer.close().await;
+            }
+        }
+    }
+
+    /// Applies what a viewer asked for over its control channel. Messages
+    /// for the sharer's UI need nothing here.
+    pub async fn on_control_message(
+        &self,
+        id: &str,
+        viewer_id: &str,
+        message: &ControlMessage,
+    ) -> Result<()> {
+        let mut map = self.preview_connections.lock().await;
+        let conn = map
+            .get_mut(id)
+            .ok_or_else(|| CaptureError::SourceNotFound(id.to_string()))?;
+        let viewer = conn
+            .viewers
+            .get_mut(viewer_id)
+            .ok_or_else(|| CaptureError::SourceNotFound(format!("{}/{}", id, viewer_id)))?;
+
+        match message {
+            ControlMessage::Pause | ControlMessage::Resume => {
+                viewer.paused = matches!(message, ControlMessage::Pause);
+                conn.update_paused();
             }
+            ControlMessage::RequestKeyframe => conn.control.request_keyframe(),
+            ControlMessage::Stats(report) => viewer.report = Some(*report),
+            // The rest is for the sharer's UI or has its own handler
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub async fn send_control(
+        &self,
+        id: &str,
+        viewer_id: &str,
+        message: &ControlMessage,
+    ) -> Result<()> {
+        let channel = {
+            let map = self.preview_connections.lock().await;
+            map.get(id)
+                .and_then(|conn| conn.viewers.get(viewer_id))
+                .map(|viewer| viewer.control_channel.clone())
+                .ok_or_else(|| CaptureError::SourceNotFound(format!("{}/{}", id, viewer_id)))?
+        };
+
+        channel.send(message).await
+    }
+
+    /// Sends `message` to every viewer of the preview but `except`. Viewers
+    /// whose channel is not open yet miss it.
+    pub async fn broadcast_control(
+        &self,
+        id: &str,
+        except: Option<&str>,
+        message: &ControlMessage,
+    ) {
+        let channels: Vec<_> = {
+            let map = self.preview_connections.lock().await;
+            map.get(id)
+                .map(|conn| {
+                    conn.viewers
+                        .iter()
+                        .filter(|(viewer_id, _)| Some(viewer_id.as_str()) != except)
+                        .map(|(_, viewer)| viewer.control_channel.clone())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        for channel in channels {
+            let _ = channel.send(message).await;
         }
     }
 
     pub async fn add_preview_ice_candidate(
         &self,
         id: String,
+        viewer_id: String,
         candidate: String,
         sdp_mid: Option<String>,
         sdp_mline_index: Option<u16>,
     ) -> Result<()> {
-        let pc = {
-            let map = self.preview_connections.lock().await;
-            map.get(&id)
-                .ok_or(CaptureError::SourceNotFound(id.clone()))?
-                .peer_conn
-                .as_ref()
-                .ok_or(CaptureError::SourceNotFound(id.clone()))?
-                .clone()
-        };
+        let pc = self.viewer(&id, &viewer_id).await?;
 
         let ice_candidate = RTCIceCandidateInit {
             candidate,
@@ -241,3 +668,216 @@ impl WebRTCServer {
         Ok(())
     }
 }
+
+// Creates an offer for a viewer and sets it as the local description
+async fn create_offer(peer_connection: &RTCPeerConnection) -> Result<RTCSessionDescription> {
+    let offer = peer_connection
+        .create_offer(None)
+        .await
+        .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+    peer_connection
+        .set_local_description(offer.clone())
+        .await
+        .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+    Ok(offer)
+}
+
+// Applies a viewer's offer and sets the answer to it as the local description
+async fn answer_offer(
+    peer_connection: &RTCPeerConnection,
+    offer: RTCSessionDescription,
+) -> Result<RTCSessionDescription> {
+    peer_connection
+        .set_remote_description(offer)
+        .await
+        .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+    let answer = peer_connection
+        .create_answer(None)
+        .await
+        .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+    peer_connection
+        .set_local_description(answer.clone())
+        .await
+        .map_err(|e| CaptureError::WebRTCError(e.to_string()))?;
+
+    Ok(answer)
+}
+
+/// Forwards peer connection, ICE and DTLS state changes to the frontend, and
+/// drops the viewer once its peer connection fails or is closed.
+fn watch_connection_state(id: &str, viewer_id: &str, peer_connection: &Arc<RTCPeerConnection>) {
+    let emit_state = || {
+        let (id, viewer_id) = (id.to_string(), viewer_id.to_string());
+        move |change: PreviewState| {
+            events::emit(
+                CONNECTION_STATE,
+                PreviewStateChanged {
+                    id: id.clone(),
+                    viewer_id: viewer_id.clone(),
+                    change,
+                },
+            )
+        }
+    };
+
+    let emit = emit_state();
+    peer_connection.on_ice_connection_state_change(Box::new(
+        move |state: RTCIceConnectionState| {
+            emit(PreviewState::Ice(state.to_string()));
+            Box::pin(async {})
+        },
+    ));
+
+    let emit = emit_state();
+    peer_connection.sctp().transport().on_state_change(Box::new(
+        move |state: RTCDtlsTransportState| {
+            emit(PreviewState::Dtls(state.to_string()));
+            Box::pin(async {})
+        },
+    ));
+
+    let emit = emit_state();
+    let (id, viewer_id) = (id.to_string(), viewer_id.to_string());
+    // Ends the viewer unless it reconnects within `DISCONNECT_GRACE`
+    let grace: std::sync::Mutex<Option<tokio::task::JoinHandle<()>>> = Default::default();
+    peer_connection.on_peer_connection_state_change(Box::new(
+        move |state: RTCPeerConnectionState| {
+            emit(PreviewState::PeerConnection(state.to_string()));
+
+            let mut grace = grace.lock().unwrap();
+            if let Some(timer) = grace.take() {
+                timer.abort();
+            }
+
+            // Spawned, since closing the viewer closes this peer and waits
+            // for its handlers
+            match state {
+                RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed => {
+                    tokio::spawn(viewer_ended(id.clone(), viewer_id.clone()));
+                }
+                RTCPeerConnectionState::Disconnected => {
+                    let ended = viewer_ended(id.clone(), viewer_id.clone());
+                    *grace = Some(tokio::spawn(async move {
+                        tokio::time::sleep(DISCONNECT_GRACE).await;
+                        // Detached, the `Closed` it causes cancels the timer
+                        tokio::spawn(ended);
+                    }));
+                }
+                _ => {}
+            }
+            Box::pin(async {})
+        },
+    ));
+}
+
+// Drops a viewer whose peer is gone, and stops the capture once nobody is
+// left watching. Viewers closed on purpose are already removed.
+async fn viewer_ended(id: String, viewer_id: String) {
+    let remaining = crate::share_screen::WEBRTC_SERVER
+        .read()
+        .await
+        .close_viewer(&id, &viewer_id)
+        .await;
+    if remaining != Some(0) {
+        return;
+    }
+
+    println!("[WebRTC] {} last viewer disconnected, stopping preview", id);
+    if let Err(e) = crate::share_screen::MANAGER
+        .write()
+        .await
+        .stop_preview(&id)
+        .await
+    {
+        eprintln!("[WebRTC] Failed to stop preview {}: {:?}", id, e);
+    }
+}
+
+/// Feeds receiver feedback into the encoder: PLI/FIR become keyframe requests,
+/// REMB and loss reports drive the target bitrate. Ends when the sender closes.
+async fn read_sender_rtcp(
+    id: String,
+    viewer_id: String,
+    sender: Arc<RTCRtpSender>,
+    control: Arc<EncoderControl>,
+    rates: Arc<ViewerRates>,
+    feedback: Arc<std::sync::Mutex<ViewerFeedback>>,
+) {
+    let mut estimator =
+        BitrateEstimator::new(control.target_kbps(), MIN_BITRATE_KBPS, control.max_kbps());
+
+    while let Ok((packets, _)) = sender.read_rtcp().await {
+        for packet in packets {
+            let packet = packet.as_any();
+
+            if packet.is::<PictureLossIndication>() {
+                feedback.lock().unwrap().pli_count += 1;
+                control.request_keyframe();
+                continue;
+            }
+            if packet.is::<FullIntraRequest>() {
+                feedback.lock().unwrap().fir_count += 1;
+                control.request_keyframe();
+                continue;
+            }
+
+            let target =
+                if let Some(remb) = packet.downcast_ref::<ReceiverEstimatedMaximumBitrate>() {
+                    estimator.on_remb(remb.bitrate)
+                } else if let Some(rr) = packet.downcast_ref::<ReceiverReport>() {
+                    feedback.lock().unwrap().on_receiver_report(rr);
+                    match rr.reports.iter().map(|r| r.fraction_lost).max() {
+                        Some(fraction_lost) => estimator.on_loss(fraction_lost),
+                        None => continue,
+                    }
+                } else {
+                    continue;
+                };
+
+            let target = rates.set(&viewer_id, target);
+            if target != control.target_kbps() {
+                println!("[WebRTC] {} target bitrate {} kbps", id, target);
+                control.set_target_kbps(target);
+            }
+        }
+    }
+}
+
+/// Writes one layer's encoded frames to its track until the encoder side
+/// goes away.
+async fn write_samples(
+    label: String,
+    codec: VideoCodec,
+    track: Arc<TrackLocalStaticSample>,
+    frame_rx: QueueReceiver<EncodedFrame>,
+    control: Arc<EncoderControl>,
+) {
+    let mut clock = SampleClock::default();
+    // Wall clock of the first frame, later ones are offset by media time
+    let mut origin: Option<(SystemTime, Duration)> = None;
+
+    while let Some(frame) = frame_rx.recv().await {
+        let (wallclock, first) = *origin.get_or_insert((SystemTime::now(), frame.timestamp));
+        let nominal = Duration::from_secs(1) / control.target_fps();
+
+        let sample = Sample {
+            duration: clock.duration_for(frame.timestamp, nominal),
+            timestamp: wallclock + frame.timestamp.saturating_sub(first),
+            data: frame.data.into(),
+            packet_timestamp: 0,
+            prev_dropped_packets: 0,
+            prev_padding_packets: 0,
+        };
+
+        if let Err(e) = track.write_sample(&sample).await {
+            eprintln!(
+                "[WebRTC] Failed to write {:?} sample for {}: {}",
+                codec, label, e
+            );
+        }
+    }
+}
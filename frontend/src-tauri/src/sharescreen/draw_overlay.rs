@@ -11,22 +11,31 @@ use std::{
 use windows::Win32::{
     Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM},
     Graphics::Gdi::{
-        CreatePen, CreateSolidBrush, DeleteObject, GetDC, GetMonitorInfoW, HMONITOR,
-        InvalidateRect, MONITORINFO, PS_SOLID, Rectangle, ReleaseDC, SelectObject, UpdateWindow,
+        CreateBitmap, CreatePatternBrush, CreatePen, CreateSolidBrush, DeleteObject, FillRect,
+        GetDC, GetMonitorInfoW, GetStockObject, HBITMAP, HBRUSH, HDC, HMONITOR, InvalidateRect,
+        LineTo, MONITORINFO, MoveToEx, NULL_BRUSH, PS_SOLID, Rectangle, ReleaseDC, SelectObject,
+        SetBkColor, SetTextColor, UpdateWindow,
     },
     System::LibraryLoader::GetModuleHandleW,
     UI::WindowsAndMessaging::*,
 };
 use windows_core::PCWSTR;
 
+use crate::share_screen::annotation::{Point, Shape, arrow_head};
+use crate::share_screen::dto::CaptureSourceType;
 use crate::sharescreen::dto::MonitorRect;
 
+/// Transparent colour of the overlay. Annotation colours are nudged off it,
+/// so every colour the presenter picks stays visible.
+const COLOR_KEY: COLORREF = COLORREF(0x00FF00FF);
+
 pub unsafe fn draw_border(hwnd: HWND, width: i32, height: i32, color: COLORREF, thickness: i32) {
     unsafe {
         let hdc = GetDC(Some(hwnd));
 
         let pen = CreatePen(PS_SOLID, thickness, color);
-        let brush = CreateSolidBrush(COLORREF(0));
+        // Clears what was drawn last time
+        let brush = CreateSolidBrush(COLOR_KEY);
 
         // Convert HPEN/HBRUSH to HGDIOBJ
         let old_pen = SelectObject(hdc, pen.into());
@@ -46,6 +55,126 @@ pub unsafe fn draw_border(hwnd: HWND, width: i32, height: i32, color: COLORREF,
     }
 }
 
+// Shapes are in source pixels and the overlay covers the source 1:1. A
+// colour keyed window has no alpha, so translucent highlights are dithered.
+pub unsafe fn draw_annotations(hwnd: HWND, shapes: &[Shape]) {
+    unsafe {
+        let hdc = GetDC(Some(hwnd));
+
+        for shape in shapes {
+            match shape {
+                Shape::Stroke {
+                    points,
+                    color,
+                    width,
+                } => with_pen(hdc, *color, *width, || polyline(hdc, points)),
+                Shape::Arrow {
+                    from,
+                    to,
+                    color,
+                    width,
+                } => with_pen(hdc, *color, *width, || {
+                    polyline(hdc, &[*from, *to]);
+                    for (a, b) in arrow_head(*from, *to, *width) {
+                        polyline(hdc, &[a, b]);
+                    }
+                }),
+                Shape::Rectangle {
+                    from,
+                    to,
+                    color,
+                    width,
+                } => with_pen(hdc, *color, *width, || {
+                    let old_brush = SelectObject(hdc, GetStockObject(NULL_BRUSH));
+                    let _ = Rectangle(hdc, from.x as i32, from.y as i32, to.x as i32, to.y as i32);
+                    SelectObject(hdc, old_brush);
+                }),
+                Shape::Highlight { from, to, color } => {
+                    let rect = RECT {
+                        left: from.x.min(to.x) as i32,
+                        top: from.y.min(to.y) as i32,
+                        right: from.x.max(to.x) as i32,
+                        bottom: from.y.max(to.y) as i32,
+                    };
+                    let (brush, pattern) = highlight_brush(hdc, *color);
+                    FillRect(hdc, &rect, brush);
+                    let _ = DeleteObject(brush.into());
+                    if let Some(pattern) = pattern {
+                        let _ = DeleteObject(pattern.into());
+                    }
+                }
+            }
+        }
+
+        let _ = ReleaseDC(Some(hwnd), hdc);
+    }
+}
+
+fn to_colorref([r, g, b, _]: [u8; 4]) -> COLORREF {
+    let color = COLORREF(r as u32 | (g as u32) << 8 | (b as u32) << 16);
+    if color == COLOR_KEY {
+        // One step off the key, indistinguishable on screen
+        COLORREF(color.0 - 1)
+    } else {
+        color
+    }
+}
+
+unsafe fn with_pen(hdc: HDC, color: [u8; 4], width: f32, draw: impl FnOnce()) {
+    unsafe {
+        let pen = CreatePen(PS_SOLID, width.round().max(1.0) as i32, to_colorref(color));
+        let old_pen = SelectObject(hdc, pen.into());
+        draw();
+        SelectObject(hdc, old_pen);
+        let _ = DeleteObject(pen.into());
+    }
+}
+
+// Opaque colours fill solid. Anything translucent gets a checkerboard of the
+// colour and the key, which reads as half transparent. The pattern bitmap
+// has to outlive the brush.
+unsafe fn highlight_brush(hdc: HDC, color: [u8; 4]) -> (HBRUSH, Option<HBITMAP>) {
+    unsafe {
+        if color[3] == 255 {
+            return (CreateSolidBrush(to_colorref(color)), None);
+        }
+
+        // Monochrome rows are word aligned, 0 bits take the text colour
+        let rows: [u8; 16] = [
+            0xAA, 0, 0x55, 0, 0xAA, 0, 0x55, 0, 0xAA, 0, 0x55, 0, 0xAA, 0, 0x55, 0,
+        ];
+        let pattern = CreateBitmap(8, 8, 1, 1, Some(rows.as_ptr().cast()));
+        let brush = CreatePatternBrush(pattern);
+
+        SetTextColor(hdc, to_colorref(color));
+        SetBkColor(hdc, COLOR_KEY);
+        (brush, Some(pattern))
+    }
+}
+
+unsafe fn polyline(hdc: HDC, points: &[Point]) {
+    unsafe {
+        let Some(first) = points.first() else {
+            return;
+        };
+
+        let _ = MoveToEx(hdc, first.x as i32, first.y as i32, None);
+        for point in points.iter().skip(1) {
+            let _ = LineTo(hdc, point.x as i32, point.y as i32);
+        }
+    }
+}
+
+// The overlay thread cannot await, so it skips a frame when the manager is busy
+fn annotations_for(source: CaptureSourceType) -> Vec<Shape> {
+    crate::share_screen::MANAGER
+        .try_read()
+        .ok()
+        .and_then(|manager| manager.annotations_for_source(&source))
+        .map(|layer| layer.shapes())
+        .unwrap_or_default()
+}
+
 fn to_pcwstr(s: &str) -> PCWSTR {
     let mut v: Vec<u16> = s.encode_utf16().collect();
     v.push(0);
@@ -92,7 +221,7 @@ pub unsafe fn create_overlay(left: i32, top: i32, width: i32, height: i32) -> HW
         )
         .expect("Failed to create overlay window");
 
-        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), 255, LWA_COLORKEY);
+        let _ = SetLayeredWindowAttributes(hwnd, COLOR_KEY, 255, LWA_COLORKEY);
         let _ = ShowWindow(hwnd, SW_SHOW);
         let _ = UpdateWindow(hwnd);
 
@@ -142,6 +271,10 @@ unsafe fn run_tracking_loop(hwnd_target: HWND, stop: Arc<AtomicBool>) {
                     let _ = InvalidateRect(Some(hwnd_overlay), None, true);
 
                     draw_border(hwnd_overlay, width, height, COLORREF(0x81B910), 4);
+                    draw_annotations(
+                        hwnd_overlay,
+                        &annotations_for(CaptureSourceType::Window(hwnd_target.0 as isize)),
+                    );
                 }
             }));
 
@@ -218,6 +351,10 @@ pub unsafe fn track_monitor(hmonitor: HMONITOR, stop: Arc<AtomicBool>) {
                     let _ = InvalidateRect(Some(hwnd_overlay), None, true);
 
                     draw_border(hwnd_overlay, width, height, COLORREF(0x81B910), 4);
+                    draw_annotations(
+                        hwnd_overlay,
+                        &annotations_for(CaptureSourceType::Monitor(hmonitor.0 as isize)),
+                    );
                 }
 
                 thread::sleep(Duration::from_millis(60));
@@ -1,5 +1,6 @@
 // src/hooks/useScreenCapture.ts
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { useState, useEffect, useRef, useCallback } from "react";
 
 export interface CaptureSource {
@@ -14,15 +15,27 @@ export interface CaptureSource {
 
 export interface PreviewOffer {
   id: string;
+  viewer_id: string;
   sdp: string;
 }
 
+// All candidate fields are null once the backend finished gathering
+interface PreviewIceCandidate {
+  id: string;
+  viewer_id: string;
+  candidate: string | null;
+  sdp_mid: string | null;
+  sdp_mline_index: number | null;
+}
+
 export const useScreenCapture = () => {
   const [monitors, setMonitors] = useState<CaptureSource[]>([]);
   const [windows, setWindows] = useState<CaptureSource[]>([]);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const peerConnectionsRef = useRef<Map<string, RTCPeerConnection>>(new Map());
+  const iceListenersRef = useRef<Map<string, UnlistenFn>>(new Map());
+  const viewerIdsRef = useRef<Map<string, string>>(new Map());
 
   // Fetch monitors with preview
   const fetchMonitors = useCallback(
@@ -150,22 +163,35 @@ export const useScreenCapture = () => {
     [],
   );
 
+  // Close the WebRTC connection, and its viewer on the backend
+  const closePreviewConnection = useCallback(async (id: string) => {
+    peerConnectionsRef.current.get(id)?.close();
+    peerConnectionsRef.current.delete(id);
+    iceListenersRef.current.get(id)?.();
+    iceListenersRef.current.delete(id);
+
+    const viewerId = viewerIdsRef.current.get(id);
+    viewerIdsRef.current.delete(id);
+    if (viewerId) {
+      try {
+        await invoke("close_preview_viewer", { id, viewerId });
+      } catch (err) {
+        console.warn(`[WebRTC] Failed to close viewer for ${id}:`, err);
+      }
+    }
+  }, []);
+
   // Stop preview stream
   const stopPreview = useCallback(async (id: string) => {
     try {
+      // Before the backend drops its viewers with the stream
+      await closePreviewConnection(id);
       await invoke("stop_preview", { id });
-
-      // Close WebRTC connection
-      const pc = peerConnectionsRef.current.get(id);
-      if (pc) {
-        pc.close();
-        peerConnectionsRef.current.delete(id);
-      }
     } catch (err) {
       console.error("Failed to stop preview:", err);
       throw err;
     }
-  }, []);
+  }, [closePreviewConnection]);
 
   // Setup WebRTC connection for preview
   const setupPreviewConnection = useCallback(
@@ -181,6 +207,11 @@ export const useScreenCapture = () => {
             },
           ],
         });
+        // Replaces an earlier connection to the same preview
+        await closePreviewConnection(id);
+        peerConnectionsRef.current.set(id, pc);
+        // Set once the backend has added this viewer
+        let viewerId: string | null = null;
 
         // Debug: Log all events
         pc.ontrack = (event) => {
@@ -228,6 +259,7 @@ export const useScreenCapture = () => {
             );
             await invoke("add_preview_ice_candidate", {
               id,
+              viewerId,
               candidate: event.candidate.candidate,
               sdpMid: event.candidate.sdpMid,
               sdpMLineIndex: event.candidate.sdpMLineIndex,
@@ -237,6 +269,43 @@ export const useScreenCapture = () => {
           }
         };
 
+        // Backend candidates can come before the offer is applied, and
+        // wait for it
+        const pendingCandidates: PreviewIceCandidate[] = [];
+        const addRemoteCandidate = (payload: PreviewIceCandidate) => {
+          if (payload.viewer_id !== viewerId) {
+            return;
+          }
+          const candidate =
+            payload.candidate === null
+              ? null
+              : {
+                  candidate: payload.candidate,
+                  sdpMid: payload.sdp_mid,
+                  sdpMLineIndex: payload.sdp_mline_index,
+                };
+          pc.addIceCandidate(candidate).catch((err) => {
+            console.error(
+              `[WebRTC] Failed to add ICE candidate for ${id}:`,
+              err,
+            );
+          });
+        };
+        const unlistenIce = await listen<PreviewIceCandidate>(
+          "share-screen-ice-candidate",
+          ({ payload }) => {
+            if (payload.id !== id) {
+              return;
+            }
+            if (pc.remoteDescription) {
+              addRemoteCandidate(payload);
+            } else {
+              pendingCandidates.push(payload);
+            }
+          },
+        );
+        iceListenersRef.current.set(id, unlistenIce);
+
         pc.oniceconnectionstatechange = () => {
           console.log(
             `[WebRTC] ICE connection state for ${id}:`,
@@ -265,6 +334,8 @@ export const useScreenCapture = () => {
         // Get offer from backend
         console.log(`[WebRTC] Getting offer from backend for ${id}`);
         const offer = await invoke<PreviewOffer>("get_preview_offer", { id });
+        viewerId = offer.viewer_id;
+        viewerIdsRef.current.set(id, viewerId);
         console.log(
           `[WebRTC] Received offer for ${id}:`,
           offer.sdp.substring(0, 100) + "...",
@@ -273,6 +344,7 @@ export const useScreenCapture = () => {
         // Set remote description
         console.log(`[WebRTC] Setting remote description for ${id}`);
         await pc.setRemoteDescription({ type: "offer", sdp: offer.sdp });
+        pendingCandidates.splice(0).forEach(addRemoteCandidate);
 
         // Create answer
         console.log(`[WebRTC] Creating answer for ${id}`);
@@ -287,12 +359,13 @@ export const useScreenCapture = () => {
 
         // Send answer to backend
         console.log(`[WebRTC] Sending answer to backend for ${id}`);
-        await invoke("accept_preview_answer", { id, sdp: answer.sdp });
+        await invoke("accept_preview_answer", {
+          id,
+          viewerId,
+          sdp: answer.sdp,
+        });
         console.log(`[WebRTC] Answer accepted by backend for ${id}`);
 
-        // Store peer connection
-        peerConnectionsRef.current.set(id, pc);
-
         // Wait a bit and check video element
         setTimeout(() => {
           console.log(`[WebRTC] Video element state for ${id}:`, {
@@ -341,19 +414,21 @@ export const useScreenCapture = () => {
           `[WebRTC] Failed to setup preview connection for ${id}:`,
           err,
         );
+        await closePreviewConnection(id);
         throw err;
       }
     },
-    [],
+    [closePreviewConnection],
   );
 
   // Cleanup on unmount
   useEffect(() => {
     return () => {
-      peerConnectionsRef.current.forEach((pc) => pc.close());
-      peerConnectionsRef.current.clear();
+      peerConnectionsRef.current.forEach((_, id) => {
+        void closePreviewConnection(id);
+      });
     };
-  }, []);
+  }, [closePreviewConnection]);
 
   return {
     monitors,